@@ -13,6 +13,10 @@ pub fn pty_spawn(
     claude_parser: State<'_, Arc<ClaudeParser>>,
     cols: u16,
     rows: u16,
+    // Cell-pixel dimensions from the frontend's font metrics, so TIOCGWINSZ
+    // reports real pixel size to graphics-capable programs.
+    pixel_width: Option<u16>,
+    pixel_height: Option<u16>,
 ) -> Result<(), String> {
     let app_clone = app.clone();
     let parser = Arc::clone(&claude_parser);
@@ -20,7 +24,7 @@ pub fn pty_spawn(
     // Clear parser state for new session
     parser.clear();
 
-    pty_manager.spawn(cols, rows, move |data| {
+    pty_manager.spawn(cols, rows, pixel_width.unwrap_or(0), pixel_height.unwrap_or(0), move |data| {
         // Emit raw output to terminal
         let _ = app_clone.emit("pty-output", data.clone());
 
@@ -66,8 +70,10 @@ pub fn pty_resize(
     pty_manager: State<'_, Arc<PtyManager>>,
     cols: u16,
     rows: u16,
+    pixel_width: Option<u16>,
+    pixel_height: Option<u16>,
 ) -> Result<(), String> {
-    pty_manager.resize(cols, rows)
+    pty_manager.resize(cols, rows, pixel_width.unwrap_or(0), pixel_height.unwrap_or(0))
 }
 
 /// Check if PTY is active
@@ -44,6 +44,10 @@ pub struct ClaudeParser {
     current_block: Mutex<String>,
     events: Mutex<Vec<ClaudeEvent>>,
     last_processed_len: Mutex<usize>,
+    /// Bytes left over from the previous `process()` call that didn't yet
+    /// form a complete UTF-8 sequence (PTY reads can split a multi-byte
+    /// character, or a `⏺`/`⎿` marker, across two chunks).
+    pending_bytes: Mutex<Vec<u8>>,
 }
 
 impl ClaudeParser {
@@ -54,9 +58,56 @@ impl ClaudeParser {
             current_block: Mutex::new(String::new()),
             events: Mutex::new(Vec::new()),
             last_processed_len: Mutex::new(0),
+            pending_bytes: Mutex::new(Vec::new()),
         }
     }
 
+    /// Decode a raw PTY chunk, carrying over any trailing bytes that don't
+    /// yet form a complete UTF-8 sequence so they can be joined with the
+    /// start of the next chunk instead of being replaced with `\u{FFFD}`.
+    /// Only sequences that are complete but malformed are treated as truly
+    /// invalid and replaced.
+    fn decode_chunk(&self, data: &[u8]) -> String {
+        let mut pending = self.pending_bytes.lock();
+        pending.extend_from_slice(data);
+
+        let mut out = String::new();
+        let mut rest: &[u8] = &pending;
+
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(s) => {
+                    out.push_str(s);
+                    rest = &[];
+                    break;
+                }
+                Err(e) => {
+                    let valid_up_to = e.valid_up_to();
+                    out.push_str(std::str::from_utf8(&rest[..valid_up_to]).unwrap());
+
+                    match e.error_len() {
+                        Some(len) => {
+                            // Complete but malformed sequence - it can never become
+                            // valid, so replace it and keep decoding the remainder.
+                            out.push('\u{FFFD}');
+                            rest = &rest[valid_up_to + len..];
+                        }
+                        None => {
+                            // Truncated sequence at the end of this chunk - stash it
+                            // for next time instead of corrupting it.
+                            rest = &rest[valid_up_to..];
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        let leftover = rest.to_vec();
+        *pending = leftover;
+        out
+    }
+
     /// Flush accumulated block as an event
     fn flush_block(state: &ParserState, block: &str, events: &mut Vec<ClaudeEvent>) {
         if block.is_empty() {
@@ -137,7 +188,7 @@ impl ClaudeParser {
 
     /// Process incoming PTY data
     pub fn process(&self, data: &[u8]) -> Vec<ClaudeEvent> {
-        let text = String::from_utf8_lossy(data);
+        let text = self.decode_chunk(data);
         let clean_text = Self::strip_ansi(&text);
 
         let mut buffer = self.buffer.lock();
@@ -402,6 +453,7 @@ impl ClaudeParser {
         *self.state.lock() = ParserState::Idle;
         *self.current_block.lock() = String::new();
         *self.last_processed_len.lock() = 0;
+        self.pending_bytes.lock().clear();
     }
 }
 
@@ -31,6 +31,8 @@ impl PtyManager {
         &self,
         cols: u16,
         rows: u16,
+        pixel_width: u16,
+        pixel_height: u16,
         on_output: impl Fn(Vec<u8>) + Send + 'static,
     ) -> Result<(), String> {
         let mut session = self.session.lock();
@@ -46,8 +48,8 @@ impl PtyManager {
             .openpty(PtySize {
                 rows,
                 cols,
-                pixel_width: 0,
-                pixel_height: 0,
+                pixel_width,
+                pixel_height,
             })
             .map_err(|e| format!("Failed to open PTY: {}", e))?;
 
@@ -118,7 +120,7 @@ impl PtyManager {
     }
 
     /// Resize PTY
-    pub fn resize(&self, cols: u16, rows: u16) -> Result<(), String> {
+    pub fn resize(&self, cols: u16, rows: u16, pixel_width: u16, pixel_height: u16) -> Result<(), String> {
         let session = self.session.lock();
         if let Some(ref s) = *session {
             s.pair
@@ -126,8 +128,8 @@ impl PtyManager {
                 .resize(PtySize {
                     rows,
                     cols,
-                    pixel_width: 0,
-                    pixel_height: 0,
+                    pixel_width,
+                    pixel_height,
                 })
                 .map_err(|e| format!("Failed to resize PTY: {}", e))?;
             Ok(())
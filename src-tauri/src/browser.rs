@@ -1,3 +1,5 @@
+use crate::media;
+use crate::now_playing::NowPlaying;
 use parking_lot::Mutex;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -12,13 +14,50 @@ pub struct BrowserState {
     webviews: HashMap<String, String>,
     /// Currently active tab
     active_tab: Option<String>,
+    /// Last URL reported for each tab, so `media_state_report` (which only
+    /// carries a DOM-scraped title) can be cross-checked/enriched against
+    /// `media::resolve_url` without the frontend having to resend the URL.
+    last_url: HashMap<String, String>,
+    /// Per-tab frame-bypass host allowlist from `BrowserOptions`. Recorded
+    /// only; the `on_web_resource_request` handler registered in
+    /// `browser_create` filters with its own copy captured at creation.
+    frame_bypass_hosts: HashMap<String, Vec<String>>,
+    /// Last reported title per tab, used by `media_state_report` to tell a
+    /// metadata change (new track) apart from a play-state transition.
+    last_media_title: HashMap<String, String>,
+    /// Profile (user-agent/proxy/partition) each tab was created with.
+    /// Recorded only; `browser_clear_session` clears the tab's webview
+    /// browsing data directly and does not read this.
+    profiles: HashMap<String, BrowserProfile>,
+    /// Active "watch party" group, if any - at most one at a time.
+    sync_group: Option<SyncGroup>,
+    /// Most recent `MediaStateReport` per tab, used to compute follower
+    /// drift against the sync group leader.
+    last_media_report: HashMap<String, MediaStateReport>,
 }
 
+/// A "watch party" group: `leader`'s reported playback state is mirrored
+/// onto `followers` within `SYNC_TOLERANCE_SECS`.
+struct SyncGroup {
+    leader: String,
+    followers: Vec<String>,
+}
+
+/// How far a follower's `current_time` may drift from the leader's before
+/// `media_state_report` issues a corrective seek.
+const SYNC_TOLERANCE_SECS: f64 = 0.5;
+
 impl BrowserState {
     pub fn new() -> Self {
         Self {
             webviews: HashMap::new(),
             active_tab: None,
+            last_url: HashMap::new(),
+            frame_bypass_hosts: HashMap::new(),
+            last_media_title: HashMap::new(),
+            profiles: HashMap::new(),
+            sync_group: None,
+            last_media_report: HashMap::new(),
         }
     }
 }
@@ -32,6 +71,106 @@ pub struct BrowserPosition {
     pub height: f64,
 }
 
+/// Per-tab opt-in options for `browser_create`.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct BrowserOptions {
+    /// Host patterns (exact host, or `*.example.com` for a subdomain
+    /// wildcard) for which `X-Frame-Options` / CSP `frame-ancestors` should
+    /// be stripped from responses so the site can load inside our webview.
+    /// Empty by default - this must be explicitly opted into per tab, never
+    /// applied globally.
+    #[serde(default)]
+    pub frame_bypass_hosts: Vec<String>,
+}
+
+/// HTTP or SOCKS5 proxy to route a tab's webview traffic through.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ProxyConfig {
+    pub scheme: String, // "http" | "socks5"
+    pub host: String,
+    pub port: u16,
+}
+
+/// Per-tab session profile: lets different tabs impersonate different
+/// browsers, route through different proxies, and keep isolated logged-in
+/// sessions (e.g. two WhatsApp Web accounts) via a named storage partition.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct BrowserProfile {
+    pub user_agent: Option<String>,
+    pub proxy: Option<ProxyConfig>,
+    /// Named data partition. Tabs that share a partition name share cookies
+    /// and storage; tabs with distinct partitions get isolated storage, and
+    /// tabs with no partition all share the default storage.
+    pub partition: Option<String>,
+}
+
+/// Reject partition names that could escape the `browser-profiles` storage
+/// directory when joined onto it with `Path::join` - an absolute-looking
+/// segment (e.g. `/etc`) replaces the base entirely, and `..` segments
+/// traverse out of it, letting a caller-supplied profile name redirect
+/// where a tab's cookies/storage are read from and written to. Only a
+/// single plain path segment is allowed.
+fn validate_partition_name(partition: &str) -> Result<(), String> {
+    if partition.is_empty()
+        || partition.contains('/')
+        || partition.contains('\\')
+        || partition == "."
+        || partition == ".."
+    {
+        return Err(format!("Invalid partition name: {}", partition));
+    }
+    Ok(())
+}
+
+/// Whether `host` matches one of `patterns`, where a pattern starting with
+/// `*.` matches the bare domain or any subdomain of it.
+fn host_matches(host: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| {
+        if let Some(suffix) = pattern.strip_prefix("*.") {
+            host == suffix || host.ends_with(&format!(".{}", suffix))
+        } else {
+            host == pattern
+        }
+    })
+}
+
+/// Strip `frame-ancestors`/`frame-src` directives from every instance of a
+/// CSP-family header (`HeaderMap` is a multi-map, and some servers emit more
+/// than one, or pair it with the legacy `x-...` name), replacing all of them
+/// with the rewritten values.
+fn rewrite_csp_directives(headers: &mut tauri::http::HeaderMap, name: &str) {
+    let rewritten: Vec<String> = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .map(|csp_str| {
+            csp_str
+                .split(';')
+                .map(|directive| directive.trim())
+                .filter(|directive| {
+                    !directive.starts_with("frame-ancestors") && !directive.starts_with("frame-src")
+                })
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+        .collect();
+
+    headers.remove(name);
+    for value in rewritten {
+        if let Ok(header_value) = tauri::http::HeaderValue::from_str(&value) {
+            headers.append(name, header_value);
+        }
+    }
+}
+
+/// Strip `X-Frame-Options` and neutralize `frame-ancestors`/`frame-src` CSP
+/// directives from a response's headers in place.
+fn strip_framing_headers(headers: &mut tauri::http::HeaderMap) {
+    headers.remove("x-frame-options");
+    rewrite_csp_directives(headers, "content-security-policy");
+    rewrite_csp_directives(headers, "x-content-security-policy");
+}
+
 /// Event payload for URL changes
 #[derive(Clone, serde::Serialize)]
 pub struct UrlChangedPayload {
@@ -50,6 +189,71 @@ pub struct MediaStateReport {
     pub current_time: f64,
 }
 
+/// A structured media control action, replacing the bare command string
+/// `media_send_command` used to take. `value`'s meaning depends on `kind`:
+/// absolute seconds for `seek`, a delta in seconds for `seek_relative`, a
+/// `0..1` fraction for `volume`, and a `0.25..2` multiplier for `rate`.
+/// Unused for `play`/`pause`/`toggle`/`next`/`prev`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct MediaCommand {
+    pub kind: MediaCommandKind,
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaCommandKind {
+    Play,
+    Pause,
+    Toggle,
+    Next,
+    Prev,
+    Seek,
+    SeekRelative,
+    Volume,
+    Rate,
+}
+
+/// Validate `command.value` against the range `command.kind` expects,
+/// returning the value (or 0.0 for value-less kinds) to embed in the JS call.
+fn validate_media_command(command: &MediaCommand) -> Result<f64, String> {
+    match command.kind {
+        MediaCommandKind::Play
+        | MediaCommandKind::Pause
+        | MediaCommandKind::Toggle
+        | MediaCommandKind::Next
+        | MediaCommandKind::Prev => Ok(0.0),
+        MediaCommandKind::Seek => {
+            let value = command.value.ok_or("seek requires a value")?;
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("seek value {} must be a finite number >= 0", value));
+            }
+            Ok(value)
+        }
+        MediaCommandKind::SeekRelative => {
+            let value = command.value.ok_or("seek_relative requires a value")?;
+            if !value.is_finite() {
+                return Err(format!("seek_relative value {} must be a finite number", value));
+            }
+            Ok(value)
+        }
+        MediaCommandKind::Volume => {
+            let value = command.value.ok_or("volume requires a value")?;
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!("volume value {} must be within 0..1", value));
+            }
+            Ok(value)
+        }
+        MediaCommandKind::Rate => {
+            let value = command.value.ok_or("rate requires a value")?;
+            if !(0.25..=2.0).contains(&value) {
+                return Err(format!("rate value {} must be within 0.25..2", value));
+            }
+            Ok(value)
+        }
+    }
+}
+
 /// Event payload for media state changes
 #[derive(Clone, serde::Serialize)]
 pub struct MediaStatePayload {
@@ -59,6 +263,16 @@ pub struct MediaStatePayload {
     pub is_playing: bool,
     pub duration: f64,
     pub current_time: f64,
+    /// Classification of the tab's last reported URL, resolved server-side
+    /// via `media::resolve_url`. `None` when the URL isn't a recognized
+    /// video/playlist/album/channel link (or none has been reported yet).
+    pub target: Option<media::UrlTarget>,
+    /// Canonical artist/album metadata, when `media::resolve_url` could
+    /// determine them. Falls back to `None` so the frontend keeps using its
+    /// DOM-scraped `title` - these are a cross-check, not a replacement.
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub artwork_url: Option<String>,
 }
 
 /// Create a browser webview for a specific tab
@@ -69,8 +283,18 @@ pub async fn browser_create(
     url: String,
     position: BrowserPosition,
     tab_id: String,
+    options: Option<BrowserOptions>,
+    profile: Option<BrowserProfile>,
 ) -> Result<(), String> {
+    let frame_bypass_hosts = options.unwrap_or_default().frame_bypass_hosts;
+    let profile = profile.unwrap_or_default();
     let mut browser_state = state.lock();
+    if !frame_bypass_hosts.is_empty() {
+        browser_state
+            .frame_bypass_hosts
+            .insert(tab_id.clone(), frame_bypass_hosts.clone());
+    }
+    browser_state.profiles.insert(tab_id.clone(), profile.clone());
 
     // Check if webview already exists for this tab
     if let Some(label) = browser_state.webviews.get(&tab_id) {
@@ -184,8 +408,15 @@ pub async fn browser_create(
                 const title = getMediaTitle();
                 const isPlaying = !video.paused && !video.ended && video.readyState > 2;
 
-                // Only report when isPlaying state actually changes
-                const stateKey = `${{isPlaying}}`;
+                // Report on play-state transitions, metadata changes
+                // (title/duration), AND periodically during steady playback
+                // (coarse current_time bucket) so track changes during
+                // autoplay/queue advances aren't dropped just because
+                // isPlaying didn't change, and so sync/now-playing position
+                // keeps advancing instead of freezing after the first report.
+                const durationBucket = Math.floor(video.duration || 0);
+                const timeBucket = Math.floor((video.currentTime || 0) / 5);
+                const stateKey = `${{isPlaying}}|${{title}}|${{durationBucket}}|${{timeBucket}}`;
                 if (stateKey === lastState) return;
                 lastState = stateKey;
 
@@ -205,8 +436,8 @@ pub async fn browser_create(
                 }} catch(e) {{}}
             }}
 
-            // Command executor for play/pause/next/prev
-            window.__executeMediaCommand = function(cmd) {{
+            // Command executor for play/pause/next/prev/seek/volume/rate
+            window.__executeMediaCommand = function(cmd, value) {{
                 const video = document.querySelector('video');
                 if (!video) return;
 
@@ -223,6 +454,18 @@ pub async fn browser_create(
                         if (video.paused) video.play();
                         else video.pause();
                         break;
+                    case 'seek':
+                        video.currentTime = value;
+                        break;
+                    case 'seek_relative':
+                        video.currentTime = Math.max(0, video.currentTime + value);
+                        break;
+                    case 'volume':
+                        video.volume = value;
+                        break;
+                    case 'rate':
+                        video.playbackRate = value;
+                        break;
                     case 'next':
                         if (platform === 'youtube' || platform === 'youtube-music') {{
                             // Try clicking the next button directly
@@ -284,12 +527,51 @@ pub async fn browser_create(
     let app_handle = app.clone();
     let tab_id_for_nav = tab_id.clone();
 
-    // Use Safari User-Agent so sites like WhatsApp Web work correctly
+    // Default to a Safari User-Agent so sites like WhatsApp Web work
+    // correctly; a profile can override this per tab.
     let safari_user_agent = "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_0) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.0 Safari/605.1.15";
+    let user_agent = profile.user_agent.clone().unwrap_or_else(|| safari_user_agent.to_string());
+
+    let frame_bypass_hosts_for_request = frame_bypass_hosts.clone();
+
+    let mut webview_builder = WebviewBuilder::new(&label, webview_url)
+        .user_agent(&user_agent)
+        .devtools(true);  // Enable devtools for debugging
+
+    // Named partitions give isolated cookies/storage per profile (e.g. two
+    // WhatsApp Web accounts); tabs with no partition fall back to default
+    // shared storage, matching the pre-profile behavior.
+    if let Some(partition) = &profile.partition {
+        validate_partition_name(partition)?;
+        let data_dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to resolve app data dir: {}", e))?
+            .join("browser-profiles")
+            .join(partition);
+        webview_builder = webview_builder.data_directory(data_dir);
+    }
+
+    // Proxy support is platform-dependent in the underlying webview engine;
+    // best-effort apply it and let the webview fall back to direct
+    // connection on platforms that don't support per-webview proxies.
+    if let Some(proxy) = &profile.proxy {
+        webview_builder = webview_builder.proxy_config(tauri::webview::ProxyConfig {
+            url: format!("{}://{}:{}", proxy.scheme, proxy.host, proxy.port),
+            no_proxy: None,
+        });
+    }
 
-    let webview_builder = WebviewBuilder::new(&label, webview_url)
-        .user_agent(safari_user_agent)
-        .devtools(true)  // Enable devtools for debugging
+    let webview_builder = webview_builder
+        .on_web_resource_request(move |request, response| {
+            if frame_bypass_hosts_for_request.is_empty() {
+                return;
+            }
+            let host = request.uri().host().unwrap_or("").to_string();
+            if host_matches(&host, &frame_bypass_hosts_for_request) {
+                strip_framing_headers(response.headers_mut());
+            }
+        })
         .on_navigation(move |url| {
             let url_string = url.to_string();
 
@@ -347,6 +629,13 @@ pub async fn browser_close(
             browser_state.active_tab = None;
         }
     }
+    browser_state.last_url.remove(&tab_id);
+    browser_state.frame_bypass_hosts.remove(&tab_id);
+    browser_state.profiles.remove(&tab_id);
+    browser_state.last_media_report.remove(&tab_id);
+    if browser_state.sync_group.as_ref().is_some_and(|g| g.leader == tab_id || g.followers.contains(&tab_id)) {
+        browser_state.sync_group = None;
+    }
 
     Ok(())
 }
@@ -487,10 +776,32 @@ pub fn browser_get_tabs(
     state.lock().webviews.keys().cloned().collect()
 }
 
+/// Wipe a tab's cookies and storage - useful for multi-account workflows
+/// where a partition's saved login needs to be dropped and re-authenticated.
+#[tauri::command]
+pub fn browser_clear_session(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<Mutex<BrowserState>>>,
+    tab_id: String,
+) -> Result<(), String> {
+    let browser_state = state.lock();
+
+    if let Some(label) = browser_state.webviews.get(&tab_id) {
+        if let Some(webview) = app.get_webview(label) {
+            webview.clear_all_browsing_data()
+                .map_err(|e| format!("Failed to clear session for tab {}: {}", tab_id, e))?;
+            log::info!("[Browser] Cleared session for tab {}", tab_id);
+        }
+    }
+
+    Ok(())
+}
+
 /// Receive URL report from injected JavaScript (for SPA navigation)
 #[tauri::command]
 pub fn browser_url_report(
     app: AppHandle,
+    state: tauri::State<'_, Arc<Mutex<BrowserState>>>,
     url: String,
     tab_id: String,
 ) -> Result<(), String> {
@@ -502,6 +813,8 @@ pub fn browser_url_report(
         return Ok(());
     }
 
+    state.lock().last_url.insert(tab_id.clone(), url.clone());
+
     log::info!("[Browser] Tab {} SPA URL change: {}", tab_id, url);
     app.emit("browser-url-changed", UrlChangedPayload { url, tab_id })
         .map_err(|e| format!("Failed to emit URL change: {}", e))
@@ -511,40 +824,166 @@ pub fn browser_url_report(
 #[tauri::command]
 pub fn media_state_report(
     app: AppHandle,
+    state: tauri::State<'_, Arc<Mutex<BrowserState>>>,
+    now_playing: tauri::State<'_, Arc<NowPlaying>>,
     report: MediaStateReport,
 ) -> Result<(), String> {
     log::info!("[Browser] Media state for tab {}: {} - playing: {}",
         report.tab_id, report.title, report.is_playing);
 
-    app.emit("media-state-changed", MediaStatePayload {
-        tab_id: report.tab_id,
-        platform: report.platform,
-        title: report.title,
+    let (metadata_changed, last_url) = {
+        let mut browser_state = state.lock();
+        let changed = browser_state
+            .last_media_title
+            .get(&report.tab_id)
+            .map_or(true, |last_title| last_title != &report.title);
+        browser_state.last_media_title.insert(report.tab_id.clone(), report.title.clone());
+        browser_state.last_media_report.insert(report.tab_id.clone(), report.clone());
+        let last_url = browser_state.last_url.get(&report.tab_id).cloned();
+        (changed, last_url)
+    };
+    let resolved = last_url.as_deref().and_then(|url| media::resolve_url(url, Some(&report.title)));
+
+    let payload = MediaStatePayload {
+        tab_id: report.tab_id.clone(),
+        platform: report.platform.clone(),
+        // Prefer the resolved entity's title (falls back to the DOM-scraped
+        // title itself when no richer metadata fetch backs it) over the raw
+        // scrape, so future metadata-fetch improvements in `media::resolve_url`
+        // are picked up here without another call site change.
+        title: resolved.as_ref().and_then(|entity| entity.title.clone()).unwrap_or(report.title),
         is_playing: report.is_playing,
         duration: report.duration,
         current_time: report.current_time,
-    }).map_err(|e| format!("Failed to emit media state: {}", e))
+        target: resolved.as_ref().map(|entity| entity.target.clone()),
+        artist: resolved.as_ref().and_then(|entity| entity.artist.clone()),
+        album: resolved.as_ref().and_then(|entity| entity.album.clone()),
+        artwork_url: resolved.and_then(|entity| entity.artwork_url),
+    };
+
+    now_playing.update(&payload);
+    sync_leader_state(&app, &state, &report.tab_id, &report.platform, report.is_playing, report.current_time);
+
+    if metadata_changed {
+        app.emit("media-metadata-changed", payload.clone())
+            .map_err(|e| format!("Failed to emit media metadata change: {}", e))?;
+    }
+
+    app.emit("media-state-changed", payload).map_err(|e| format!("Failed to emit media state: {}", e))
 }
 
-/// Send media command to webview (play, pause, next, prev)
+/// If `tab_id` is the leader of an active sync group, bring every follower's
+/// playback within `SYNC_TOLERANCE_SECS` of the leader's reported state.
+/// Skips entirely if the leader itself is on an `html5`/`unknown` platform
+/// page - its `current_time`/`is_playing` aren't reliable enough to mirror
+/// onto followers - and, symmetrically, skips any individual follower on
+/// such a page, since there's no reliable `video` element state to
+/// reconcile against on those pages either.
+fn sync_leader_state(
+    app: &AppHandle,
+    state: &tauri::State<'_, Arc<Mutex<BrowserState>>>,
+    tab_id: &str,
+    leader_platform: &str,
+    leader_is_playing: bool,
+    leader_current_time: f64,
+) {
+    if leader_platform == "html5" || leader_platform == "unknown" {
+        return;
+    }
+
+    let browser_state = state.lock();
+    let Some(group) = &browser_state.sync_group else { return };
+    if group.leader != tab_id {
+        return;
+    }
+
+    for follower in &group.followers {
+        let Some(follower_report) = browser_state.last_media_report.get(follower) else { continue };
+        if follower_report.platform == "html5" || follower_report.platform == "unknown" {
+            continue;
+        }
+
+        if follower_report.is_playing != leader_is_playing {
+            let kind = if leader_is_playing { "play" } else { "pause" };
+            let _ = dispatch_media_command(app, &browser_state, follower, kind, 0.0);
+        }
+
+        let drift = (follower_report.current_time - leader_current_time).abs();
+        if drift > SYNC_TOLERANCE_SECS {
+            let _ = dispatch_media_command(app, &browser_state, follower, "seek", leader_current_time);
+        }
+    }
+}
+
+/// Start a watch-party sync group: `leader`'s playback is mirrored onto the
+/// other entries in `tab_ids` on every subsequent `media_state_report`.
+#[tauri::command]
+pub fn media_sync_group(
+    state: tauri::State<'_, Arc<Mutex<BrowserState>>>,
+    tab_ids: Vec<String>,
+    leader: String,
+) -> Result<(), String> {
+    if !tab_ids.contains(&leader) {
+        return Err(format!("Leader tab {} is not in tab_ids", leader));
+    }
+
+    let followers = tab_ids.into_iter().filter(|id| id != &leader).collect();
+    state.lock().sync_group = Some(SyncGroup { leader, followers });
+    Ok(())
+}
+
+/// Tear down the active watch-party sync group, if any.
+#[tauri::command]
+pub fn media_sync_stop(state: tauri::State<'_, Arc<Mutex<BrowserState>>>) -> Result<(), String> {
+    state.lock().sync_group = None;
+    Ok(())
+}
+
+/// Send a structured media command (play/pause/toggle/next/prev, or a
+/// parameterized seek/seek_relative/volume/rate) to a tab's webview.
 #[tauri::command]
 pub async fn media_send_command(
     app: AppHandle,
     state: tauri::State<'_, Arc<Mutex<BrowserState>>>,
     tab_id: String,
-    command: String,
+    command: MediaCommand,
 ) -> Result<(), String> {
+    let value = validate_media_command(&command)?;
+    let kind = match command.kind {
+        MediaCommandKind::Play => "play",
+        MediaCommandKind::Pause => "pause",
+        MediaCommandKind::Toggle => "toggle",
+        MediaCommandKind::Next => "next",
+        MediaCommandKind::Prev => "prev",
+        MediaCommandKind::Seek => "seek",
+        MediaCommandKind::SeekRelative => "seek_relative",
+        MediaCommandKind::Volume => "volume",
+        MediaCommandKind::Rate => "rate",
+    };
+
     let browser_state = state.lock();
+    dispatch_media_command(&app, &browser_state, &tab_id, kind, value)
+}
 
-    if let Some(label) = browser_state.webviews.get(&tab_id) {
+/// Evaluate `window.__executeMediaCommand(kind, value)` in a tab's webview.
+/// Shared by `media_send_command` and the watch-party drift corrector in
+/// `media_state_report`, so both go through the same JS entry point.
+fn dispatch_media_command(
+    app: &AppHandle,
+    browser_state: &BrowserState,
+    tab_id: &str,
+    kind: &str,
+    value: f64,
+) -> Result<(), String> {
+    if let Some(label) = browser_state.webviews.get(tab_id) {
         if let Some(webview) = app.get_webview(label) {
             let js_command = format!(
-                r#"if (window.__executeMediaCommand) {{ window.__executeMediaCommand('{}'); }}"#,
-                command
+                r#"if (window.__executeMediaCommand) {{ window.__executeMediaCommand('{}', {}); }}"#,
+                kind, value
             );
             webview.eval(&js_command)
                 .map_err(|e| format!("Failed to execute media command: {}", e))?;
-            log::info!("[Browser] Sent media command '{}' to tab {}", command, tab_id);
+            log::info!("[Browser] Sent media command '{}' to tab {}", kind, tab_id);
         }
     }
 
@@ -0,0 +1,367 @@
+use crate::media::{self, UrlTarget};
+use futures::stream::{self, StreamExt};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once};
+use tauri::{AppHandle, Emitter};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+/// How many downloads run at once, mirroring rustypipe-downloader's "videos
+/// downloaded in parallel" worker count.
+const MAX_PARALLEL_DOWNLOADS: usize = 3;
+
+/// Requested quality for a download. Mirrors rustypipe-downloader's
+/// `--audio` / `--resolution` CLI flags.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DownloadQuality {
+    AudioOnly,
+    Resolution { height: u32 },
+}
+
+/// Options for `media_download_start`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct MediaDownloadOptions {
+    pub quality: DownloadQuality,
+    /// Directory to write the finished file into.
+    pub destination_dir: String,
+}
+
+/// Progress for a single in-flight download, emitted as `download-progress`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DownloadProgress {
+    pub id: String,
+    pub tab_id: String,
+    pub percent: f64,
+    pub bytes_per_sec: f64,
+    pub eta_seconds: Option<f64>,
+}
+
+/// Terminal outcome of a download, emitted as `download-complete`.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DownloadOutcome {
+    Finished { path: String },
+    Cancelled,
+    Failed { error: String },
+}
+
+/// Public snapshot of a download's state, returned by `media_download_list`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DownloadEntry {
+    pub id: String,
+    pub tab_id: String,
+    pub url: String,
+    pub destination_dir: String,
+}
+
+struct DownloadJob {
+    entry: DownloadEntry,
+    options: MediaDownloadOptions,
+    cancel: Arc<AtomicBool>,
+    app: AppHandle,
+}
+
+struct DownloadHandle {
+    entry: DownloadEntry,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Tracks queued/in-flight downloads and fans new jobs out to a fixed-size
+/// worker pool, the same lazily-started-singleton-background-task shape
+/// `pty::PtyManager` uses for its I/O reactor: the dispatcher needs an
+/// `AppHandle`, which isn't available at `DownloadState::new()` time, so it
+/// is started on first use via `Once` instead.
+pub struct DownloadState {
+    downloads: Mutex<HashMap<String, DownloadHandle>>,
+    next_id: Mutex<u64>,
+    dispatcher_started: Once,
+    sender: Mutex<Option<UnboundedSender<DownloadJob>>>,
+}
+
+impl DownloadState {
+    pub fn new() -> Self {
+        Self {
+            downloads: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(0),
+            dispatcher_started: Once::new(),
+            sender: Mutex::new(None),
+        }
+    }
+
+    fn alloc_id(&self) -> String {
+        let mut next_id = self.next_id.lock();
+        *next_id += 1;
+        format!("dl-{}", *next_id)
+    }
+
+    /// Start the dispatcher task the first time a download is requested.
+    fn ensure_dispatcher(self: &Arc<Self>, app: &AppHandle) -> UnboundedSender<DownloadJob> {
+        self.dispatcher_started.call_once(|| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<DownloadJob>();
+            *self.sender.lock() = Some(tx);
+            let state = self.clone();
+            tauri::async_runtime::spawn(async move {
+                let dispatch = async_stream_from_receiver(&mut rx)
+                    .map(|job| {
+                        let state = state.clone();
+                        async move {
+                            let id = job.entry.id.clone();
+                            let app = job.app.clone();
+                            let tab_id = job.entry.tab_id.clone();
+                            let outcome = run_download(&job).await;
+                            let _ = app.emit("download-complete", (&id, &outcome));
+                            let _ = tab_id;
+                            state.downloads.lock().remove(&id);
+                        }
+                    })
+                    .buffer_unordered(MAX_PARALLEL_DOWNLOADS);
+                tokio::pin!(dispatch);
+                while dispatch.next().await.is_some() {}
+            });
+        });
+        self.sender.lock().clone().expect("dispatcher always sets sender before returning")
+    }
+}
+
+/// Adapt an mpsc receiver into a `Stream` without pulling in `tokio-stream`
+/// as a dependency just for this one conversion.
+fn async_stream_from_receiver(
+    rx: &mut mpsc::UnboundedReceiver<DownloadJob>,
+) -> impl stream::Stream<Item = DownloadJob> + '_ {
+    stream::unfold(rx, |rx| async move { rx.recv().await.map(|job| (job, rx)) })
+}
+
+/// Turn a title into a filesystem-safe filename (filenamify-style): replace
+/// characters that are illegal or awkward across Windows/macOS/Linux
+/// filesystems with `_`, and trim trailing dots/spaces.
+fn sanitize_filename(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = sanitized
+        .trim()
+        .trim_end_matches(|c: char| c == '.' || c.is_whitespace());
+    if trimmed.is_empty() {
+        "download".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Parse a yt-dlp `--newline` progress line, e.g.
+/// `[download]  42.0% of 10.00MiB at 1.20MiB/s ETA 00:05`.
+fn parse_progress_line(line: &str) -> Option<(f64, Option<f64>, Option<f64>)> {
+    if !line.trim_start().starts_with("[download]") {
+        return None;
+    }
+
+    let percent = line
+        .split_whitespace()
+        .find(|tok| tok.ends_with('%'))
+        .and_then(|tok| tok.trim_end_matches('%').parse::<f64>().ok())?;
+
+    let speed = line
+        .find("at ")
+        .and_then(|idx| line[idx + 3..].split_whitespace().next())
+        .and_then(parse_size_with_suffix);
+
+    let eta = line
+        .find("ETA ")
+        .and_then(|idx| line[idx + 4..].split_whitespace().next())
+        .and_then(parse_eta);
+
+    Some((percent, speed, eta))
+}
+
+/// Parse sizes like `1.20MiB/s` or `512KiB/s` into bytes/sec.
+fn parse_size_with_suffix(token: &str) -> Option<f64> {
+    let token = token.trim_end_matches("/s");
+    let split_at = token.find(|c: char| c.is_alphabetic())?;
+    let (number, unit) = token.split_at(split_at);
+    let value: f64 = number.parse().ok()?;
+    let multiplier = match unit {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some(value * multiplier)
+}
+
+/// Parse a `HH:MM:SS` or `MM:SS` ETA into seconds.
+fn parse_eta(token: &str) -> Option<f64> {
+    let parts: Vec<f64> = token.split(':').filter_map(|p| p.parse().ok()).collect();
+    match parts.as_slice() {
+        [h, m, s] => Some(h * 3600.0 + m * 60.0 + s),
+        [m, s] => Some(m * 60.0 + s),
+        _ => None,
+    }
+}
+
+/// Run one download job to completion (or cancellation), reporting progress
+/// via `download-progress` events rather than a return value since the
+/// dispatcher isn't awaiting any single job directly.
+async fn run_download(job: &DownloadJob) -> DownloadOutcome {
+    let DownloadJob { entry, options, cancel, app } = job;
+
+    let mut args = vec!["--newline".to_string(), "--no-playlist".to_string()];
+    match &options.quality {
+        DownloadQuality::AudioOnly => {
+            args.push("-x".to_string());
+            args.push("--audio-format".to_string());
+            args.push("mp3".to_string());
+        }
+        DownloadQuality::Resolution { height } => {
+            args.push("-f".to_string());
+            args.push(format!(
+                "bestvideo[height<={}]+bestaudio/best[height<={}]",
+                height, height
+            ));
+        }
+    }
+
+    let out_template = format!(
+        "{}/%(title)s.%(ext)s",
+        options.destination_dir.trim_end_matches('/')
+    );
+    args.push("-o".to_string());
+    args.push(out_template);
+    args.push(entry.url.clone());
+
+    let mut child = match Command::new("yt-dlp")
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            return DownloadOutcome::Failed {
+                error: format!("Failed to start yt-dlp: {}", e),
+            }
+        }
+    };
+
+    let stdout = match child.stdout.take() {
+        Some(stdout) => stdout,
+        None => {
+            return DownloadOutcome::Failed {
+                error: "yt-dlp produced no stdout".to_string(),
+            }
+        }
+    };
+    let mut lines = BufReader::new(stdout).lines();
+    let mut last_path: Option<String> = None;
+
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            let _ = child.kill().await;
+            return DownloadOutcome::Cancelled;
+        }
+
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                if let Some(dest) = line.strip_prefix("[download] Destination: ") {
+                    last_path = Some(sanitize_destination(dest.trim(), &options.destination_dir));
+                }
+                if let Some((percent, bytes_per_sec, eta_seconds)) = parse_progress_line(&line) {
+                    let _ = app.emit(
+                        "download-progress",
+                        DownloadProgress {
+                            id: entry.id.clone(),
+                            tab_id: entry.tab_id.clone(),
+                            percent,
+                            bytes_per_sec: bytes_per_sec.unwrap_or(0.0),
+                            eta_seconds,
+                        },
+                    );
+                }
+            }
+            Ok(None) => break,
+            Err(_) => break,
+        }
+    }
+
+    match child.wait().await {
+        Ok(status) if status.success() => DownloadOutcome::Finished {
+            path: last_path.unwrap_or_else(|| entry.destination_dir.clone()),
+        },
+        Ok(status) => DownloadOutcome::Failed {
+            error: format!("yt-dlp exited with {}", status),
+        },
+        Err(e) => DownloadOutcome::Failed { error: e.to_string() },
+    }
+}
+
+/// yt-dlp already sanitizes filenames it writes, but we still run the
+/// reported destination through `sanitize_filename` before handing it back
+/// to the frontend, since it's rendered directly in the downloads list.
+fn sanitize_destination(reported_path: &str, destination_dir: &str) -> String {
+    match reported_path.rsplit_once('/') {
+        Some((_, filename)) => format!("{}/{}", destination_dir.trim_end_matches('/'), sanitize_filename(filename)),
+        None => format!("{}/{}", destination_dir.trim_end_matches('/'), sanitize_filename(reported_path)),
+    }
+}
+
+/// Start downloading the resolved media for a tab. The URL is resolved via
+/// `media::resolve_url` so we only ever hand a well-formed video/playlist
+/// link to the downloader.
+#[tauri::command]
+pub fn media_download_start(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<DownloadState>>,
+    tab_id: String,
+    url: String,
+    options: MediaDownloadOptions,
+) -> Result<String, String> {
+    let resolved = media::resolve_url(&url, None).ok_or_else(|| format!("Not a recognized media URL: {}", url))?;
+    if !matches!(resolved.target, UrlTarget::Video { .. } | UrlTarget::Playlist { .. }) {
+        return Err("Only video and playlist URLs can be downloaded".to_string());
+    }
+
+    let id = state.alloc_id();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let entry = DownloadEntry {
+        id: id.clone(),
+        tab_id: tab_id.clone(),
+        url,
+        destination_dir: options.destination_dir.clone(),
+    };
+    state
+        .downloads
+        .lock()
+        .insert(id.clone(), DownloadHandle { entry: entry.clone(), cancel: cancel.clone() });
+
+    let sender = state.inner().clone().ensure_dispatcher(&app);
+    sender
+        .send(DownloadJob { entry, options, cancel, app })
+        .map_err(|_| "Download dispatcher is no longer running".to_string())?;
+
+    Ok(id)
+}
+
+/// Cancel an in-flight download by id. A no-op if it already finished.
+#[tauri::command]
+pub fn media_download_cancel(state: tauri::State<'_, Arc<DownloadState>>, id: String) -> Result<(), String> {
+    if let Some(handle) = state.downloads.lock().get(&id) {
+        handle.cancel.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// List all currently tracked (queued or in-flight) downloads.
+#[tauri::command]
+pub fn media_download_list(state: tauri::State<'_, Arc<DownloadState>>) -> Vec<DownloadEntry> {
+    state.downloads.lock().values().map(|h| h.entry.clone()).collect()
+}
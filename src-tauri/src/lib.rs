@@ -1,10 +1,19 @@
+mod browser;
+mod downloads;
+mod media;
+mod now_playing;
 mod pty;
 
+use browser::BrowserState;
+use downloads::DownloadState;
+use now_playing::NowPlaying;
 use pty::PtyManager;
 use std::sync::Arc;
 use std::process::Command;
+use std::thread;
+use std::io::Read;
 use parking_lot::Mutex;
-use tauri::RunEvent;
+use tauri::{AppHandle, Emitter, RunEvent};
 
 /// Get the NVM node bin path, respecting user's default alias or falling back to latest version
 /// This ensures bundled apps use the same node version as the user's terminal
@@ -63,6 +72,112 @@ fn get_nvm_node_bin(home: &str) -> Option<String> {
     }
 }
 
+/// fnm stores each installed version under `node-versions/<version>/installation/bin`
+/// and points `aliases/default` at the active one via a symlink.
+fn get_fnm_node_bin(home: &str) -> Option<String> {
+    let fnm_dir = ["{}/.local/share/fnm", "{}/.fnm"]
+        .iter()
+        .map(|template| template.replace("{}", home))
+        .find(|dir| std::path::Path::new(dir).exists())?;
+
+    let versions_dir = format!("{}/node-versions", fnm_dir);
+    let mut versions: Vec<String> = match std::fs::read_dir(&versions_dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|name| name.starts_with('v'))
+            .collect(),
+        Err(_) => return None,
+    };
+    if versions.is_empty() {
+        return None;
+    }
+
+    let default_link = format!("{}/aliases/default", fnm_dir);
+    let selected_version = std::fs::read_link(&default_link)
+        .ok()
+        // The symlink points at `node-versions/<version>/installation`, so
+        // the version is the *parent's* file name, not the link target's own
+        // (which is always the literal "installation").
+        .and_then(|target| target.parent().and_then(|p| p.file_name()).map(|f| f.to_string_lossy().to_string()))
+        .filter(|v| versions.contains(v))
+        .unwrap_or_else(|| {
+            sort_versions_semver(&mut versions);
+            versions.last().cloned().unwrap_or_default()
+        });
+
+    let node_bin = format!("{}/{}/installation/bin", versions_dir, selected_version);
+    std::path::Path::new(&node_bin).exists().then_some(node_bin)
+}
+
+/// Volta shims live directly in `~/.volta/bin` and pick the right version
+/// themselves, so there's no per-version directory to resolve.
+fn get_volta_node_bin(home: &str) -> Option<String> {
+    let volta_bin = format!("{}/.volta/bin", home);
+    std::path::Path::new(&volta_bin).exists().then_some(volta_bin)
+}
+
+/// asdf installs Node under `~/.asdf/installs/nodejs/<version>/bin`. Honors
+/// a home-level `.tool-versions` if present, otherwise picks the latest
+/// installed version.
+fn get_asdf_node_bin(home: &str) -> Option<String> {
+    let installs_dir = format!("{}/.asdf/installs/nodejs", home);
+    let mut versions: Vec<String> = match std::fs::read_dir(&installs_dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .collect(),
+        Err(_) => return None,
+    };
+    if versions.is_empty() {
+        return None;
+    }
+
+    let tool_versions = std::fs::read_to_string(format!("{}/.tool-versions", home)).ok();
+    let pinned_version = tool_versions.as_ref().and_then(|contents| {
+        contents.lines().find_map(|line| {
+            let mut parts = line.split_whitespace();
+            if parts.next()? == "nodejs" {
+                parts.next().map(|v| v.to_string())
+            } else {
+                None
+            }
+        })
+    });
+
+    let selected_version = match pinned_version {
+        Some(version) if versions.contains(&version) => version,
+        _ => {
+            sort_versions_semver(&mut versions);
+            versions.last()?.clone()
+        }
+    };
+
+    let node_bin = format!("{}/{}/bin", installs_dir, selected_version);
+    std::path::Path::new(&node_bin).exists().then_some(node_bin)
+}
+
+/// `n` installs directly into `~/n/bin` by default, or `$N_PREFIX/bin` when
+/// `N_PREFIX` is set.
+fn get_n_node_bin(home: &str) -> Option<String> {
+    let n_bin = std::env::var("N_PREFIX")
+        .map(|prefix| format!("{}/bin", prefix))
+        .unwrap_or_else(|_| format!("{}/n/bin", home));
+    std::path::Path::new(&n_bin).exists().then_some(n_bin)
+}
+
+/// Resolve the node bin directory to prepend onto `PATH`, probing version
+/// managers in priority order so `git`, `claude`, and MCP CLIs launched
+/// through this app see the same node the user's terminal would, regardless
+/// of which manager they use.
+fn resolve_node_bin(home: &str) -> Option<String> {
+    get_fnm_node_bin(home)
+        .or_else(|| get_volta_node_bin(home))
+        .or_else(|| get_asdf_node_bin(home))
+        .or_else(|| get_n_node_bin(home))
+        .or_else(|| get_nvm_node_bin(home))
+}
+
 /// Sort node versions by semver (e.g., v18.20.8 < v20.19.5 < v22.16.0)
 fn sort_versions_semver(versions: &mut Vec<String>) {
     versions.sort_by(|a, b| {
@@ -81,44 +196,335 @@ fn sort_versions_semver(versions: &mut Vec<String>) {
     });
 }
 
-/// Build extended PATH with NVM, Homebrew, and common locations
-/// Same logic as pty.rs for consistency across all command execution
-fn build_extended_path() -> String {
+/// OS path-list separator: `;` on Windows, `:` everywhere else.
+fn path_separator() -> char {
+    if cfg!(target_os = "windows") { ';' } else { ':' }
+}
+
+/// Split a combined path list on `separator`, drop empty segments and ones
+/// that don't resolve to an existing path, and deduplicate by
+/// canonicalized path. When a directory appears more than once, the
+/// *earlier* occurrence is dropped and the *later* one is kept in its
+/// (later) position - so a duplicate always ends up at its lowest-priority
+/// slot rather than silently shadowing whatever comes after it.
+fn normalize_pathlist(combined: &str, separator: char) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    let mut index_of: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+
+    for segment in combined.split(separator) {
+        if segment.is_empty() {
+            continue;
+        }
+        let path = std::path::Path::new(segment);
+        if !path.exists() {
+            continue;
+        }
+        let canonical = path
+            .canonicalize()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| segment.to_string());
+
+        if let Some(&earlier_index) = index_of.get(&canonical) {
+            result.remove(earlier_index);
+            for index in index_of.values_mut() {
+                if *index > earlier_index {
+                    *index -= 1;
+                }
+            }
+        }
+
+        index_of.insert(canonical, result.len());
+        result.push(segment.to_string());
+    }
+
+    result
+}
+
+/// Build extended PATH with NVM, Homebrew/package-manager, and common
+/// per-platform tool locations, deduplicated via `normalize_pathlist`.
+pub(crate) fn build_extended_path() -> String {
     let home = std::env::var("HOME").unwrap_or_default();
     let current_path = std::env::var("PATH").unwrap_or_default();
+    let separator = path_separator();
 
-    let mut paths = vec![
-        "/opt/homebrew/bin".to_string(),      // Homebrew Apple Silicon
-        "/opt/homebrew/sbin".to_string(),
-        "/usr/local/bin".to_string(),         // Homebrew Intel / system
-        "/usr/local/sbin".to_string(),
-        format!("{}/.local/bin", home),       // User local
-        "/usr/bin".to_string(),
-        "/bin".to_string(),
-        "/usr/sbin".to_string(),
-        "/sbin".to_string(),
-    ];
+    let mut paths: Vec<String> = Vec::new();
 
-    // Add NVM node bin if available (respects user's default alias)
-    if let Some(nvm_bin) = get_nvm_node_bin(&home) {
-        paths.insert(0, nvm_bin);
+    // Add the resolved version-manager node bin first (fnm/Volta/asdf/n/NVM,
+    // in that priority order), so it wins over any same-version node dir
+    // found later in PATH.
+    if let Some(node_bin) = resolve_node_bin(&home) {
+        paths.push(node_bin);
+    }
+
+    if cfg!(target_os = "macos") {
+        paths.extend([
+            "/opt/homebrew/bin".to_string(), // Homebrew Apple Silicon
+            "/opt/homebrew/sbin".to_string(),
+            "/usr/local/bin".to_string(), // Homebrew Intel / system
+            "/usr/local/sbin".to_string(),
+            format!("{}/.local/bin", home),
+            "/usr/bin".to_string(),
+            "/bin".to_string(),
+            "/usr/sbin".to_string(),
+            "/sbin".to_string(),
+        ]);
+    } else if cfg!(target_os = "windows") {
+        let program_files = std::env::var("ProgramFiles").unwrap_or_else(|_| r"C:\Program Files".to_string());
+        let program_files_x86 =
+            std::env::var("ProgramFiles(x86)").unwrap_or_else(|_| r"C:\Program Files (x86)".to_string());
+        let appdata = std::env::var("APPDATA").unwrap_or_default();
+        paths.extend([
+            format!("{}\\nodejs", program_files),
+            format!("{}\\Git\\cmd", program_files),
+            format!("{}\\Git\\cmd", program_files_x86),
+            format!("{}\\npm", appdata),
+        ]);
+    } else {
+        // Linux (and other Unix): common user-local, Nix, Flatpak, and Snap
+        // export dirs in addition to the usual system locations.
+        paths.extend([
+            format!("{}/.local/bin", home),
+            format!("{}/.nix-profile/bin", home),
+            "/nix/var/nix/profiles/default/bin".to_string(),
+            format!("{}/.local/share/flatpak/exports/bin", home),
+            "/var/lib/flatpak/exports/bin".to_string(),
+            "/snap/bin".to_string(),
+            "/usr/local/bin".to_string(),
+            "/usr/local/sbin".to_string(),
+            "/usr/bin".to_string(),
+            "/bin".to_string(),
+            "/usr/sbin".to_string(),
+            "/sbin".to_string(),
+        ]);
     }
 
     if !current_path.is_empty() {
         paths.push(current_path);
     }
 
-    paths.join(":")
+    let combined = paths.join(&separator.to_string());
+    normalize_pathlist(&combined, separator).join(&separator.to_string())
 }
 
-/// Execute a shell command with proper environment variables
-/// CRITICAL: macOS bundled apps have limited environment, so we explicitly
-/// set HOME, USER, SHELL, PATH (with NVM/Homebrew) for all commands.
-/// This fixes git, mcp, and other CLI tools not working in bundled app.
+/// Readiness status for a single tool this app depends on, as reported by
+/// `detect_environment`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub found: bool,
+    pub path: Option<String>,
+    pub version: Option<String>,
+    /// Which manager or PATH entry provided this tool, e.g. "fnm", "NVM",
+    /// "Homebrew (Apple Silicon)", or "PATH".
+    pub source: Option<String>,
+}
+
+/// Find `binary_name` in `search_path` (a `build_extended_path`-style
+/// combined path list), returning its absolute path.
+fn find_in_path(search_path: &str, binary_name: &str) -> Option<String> {
+    search_path.split(path_separator()).find_map(|dir| {
+        if dir.is_empty() {
+            return None;
+        }
+        let candidate = std::path::Path::new(dir).join(binary_name);
+        candidate.exists().then(|| candidate.to_string_lossy().to_string())
+    })
+}
+
+/// Run `path --version` (or `-v` for tools that don't support `--version`)
+/// and return the first line of output, trimmed.
+fn probe_version(path: &str, flag: &str) -> Option<String> {
+    let output = Command::new(path).arg(flag).output().ok()?;
+    let text = if output.status.success() {
+        String::from_utf8_lossy(&output.stdout).to_string()
+    } else {
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+    text.lines().next().map(|line| line.trim().to_string())
+}
+
+/// Which version manager (if any) provided the resolved node bin dir -
+/// mirrors the probe order in `resolve_node_bin`.
+fn node_manager_source(home: &str) -> Option<&'static str> {
+    if get_fnm_node_bin(home).is_some() {
+        Some("fnm")
+    } else if get_volta_node_bin(home).is_some() {
+        Some("Volta")
+    } else if get_asdf_node_bin(home).is_some() {
+        Some("asdf")
+    } else if get_n_node_bin(home).is_some() {
+        Some("n")
+    } else if get_nvm_node_bin(home).is_some() {
+        Some("NVM")
+    } else {
+        None
+    }
+}
+
+fn tool_status(search_path: &str, name: &str, binary_name: &str, version_flag: &str, source: Option<&str>) -> ToolStatus {
+    match find_in_path(search_path, binary_name) {
+        Some(path) => ToolStatus {
+            name: name.to_string(),
+            found: true,
+            version: probe_version(&path, version_flag),
+            source: source.map(|s| s.to_string()).or(Some("PATH".to_string())),
+            path: Some(path),
+        },
+        None => ToolStatus { name: name.to_string(), found: false, path: None, version: None, source: None },
+    }
+}
+
+/// Report the resolved versions and absolute paths of the tools this app
+/// depends on, so the frontend can render a readiness checklist and show
+/// exactly which `PATH` entry (or version manager) a binary was found in -
+/// the backend-facing equivalent of `tauri info`.
 #[tauri::command]
-fn execute_command(cmd: String, cwd: String) -> Result<String, String> {
+fn detect_environment() -> Vec<ToolStatus> {
+    let home = std::env::var("HOME").unwrap_or_default();
+    let search_path = build_extended_path();
+    let node_source = node_manager_source(&home);
+
+    let mut tools = vec![
+        tool_status(&search_path, "node", "node", "--version", node_source),
+        tool_status(&search_path, "npm", "npm", "--version", node_source),
+        tool_status(&search_path, "git", "git", "--version", None),
+        tool_status(&search_path, "claude", "claude", "--version", None),
+    ];
+
+    let homebrew_candidates: [(&str, &str); 2] = [
+        ("/opt/homebrew/bin/brew", "Homebrew (Apple Silicon)"),
+        ("/usr/local/bin/brew", "Homebrew (Intel)"),
+    ];
+    for (brew_path, label) in homebrew_candidates {
+        if std::path::Path::new(brew_path).exists() {
+            tools.push(ToolStatus {
+                name: "brew".to_string(),
+                found: true,
+                version: probe_version(brew_path, "--version"),
+                source: Some(label.to_string()),
+                path: Some(brew_path.to_string()),
+            });
+        }
+    }
+
+    tools
+}
+
+/// Detect whether the app is running inside a Flatpak sandbox.
+fn is_flatpak() -> bool {
+    std::path::Path::new("/.flatpak-info").exists()
+}
+
+/// Detect whether the app is running inside a Snap sandbox.
+fn is_snap() -> bool {
+    std::env::var("SNAP").is_ok()
+}
+
+/// Detect whether the app is running as an AppImage.
+fn is_appimage() -> bool {
+    std::env::var("APPDIR").is_ok() || std::env::var("APPIMAGE").is_ok()
+}
+
+/// The sandbox install root to strip from inherited library/module search
+/// paths, if the app is running sandboxed at all.
+fn sandbox_prefix() -> Option<String> {
+    if is_flatpak() {
+        Some("/app".to_string())
+    } else if is_snap() {
+        std::env::var("SNAP").ok()
+    } else if is_appimage() {
+        std::env::var("APPDIR").ok()
+    } else {
+        None
+    }
+}
+
+/// Drop colon-separated entries of `value` that point inside `prefix` (the
+/// sandbox install root). Returns `None` if nothing remains - callers should
+/// omit the variable entirely rather than setting it to an empty string,
+/// since an empty value doesn't behave like an unset one for every loader.
+fn strip_sandbox_paths(value: &str, prefix: &str) -> Option<String> {
+    let remaining: Vec<&str> = value
+        .split(':')
+        .filter(|entry| !entry.is_empty() && !entry.starts_with(prefix))
+        .collect();
+    if remaining.is_empty() {
+        None
+    } else {
+        Some(remaining.join(":"))
+    }
+}
+
+/// Minimal env-setting surface shared by `std::process::Command`
+/// (`execute_command`/`execute_command_stream`) and
+/// `portable_pty::CommandBuilder` (`PtyManager::spawn`), so the sandbox
+/// normalization and platform-var injection below run identically for
+/// both instead of only covering one-off commands.
+pub(crate) trait EnvTarget {
+    fn set_env(&mut self, key: &str, value: &str);
+    fn remove_env(&mut self, key: &str);
+}
+
+impl EnvTarget for Command {
+    fn set_env(&mut self, key: &str, value: &str) {
+        self.env(key, value);
+    }
+    fn remove_env(&mut self, key: &str) {
+        self.env_remove(key);
+    }
+}
+
+impl EnvTarget for portable_pty::CommandBuilder {
+    fn set_env(&mut self, key: &str, value: &str) {
+        self.env(key, value);
+    }
+    fn remove_env(&mut self, key: &str) {
+        self.env_remove(key);
+    }
+}
+
+/// Flatpak/Snap/AppImage runtimes inject their own `LD_LIBRARY_PATH`,
+/// `GST_PLUGIN_PATH`, `GIO_MODULE_DIR`, and XDG dirs, which leak into
+/// spawned children and break host `git`/`node`/editors that expect to see
+/// the host's own libraries. When sandboxed, strip the sandbox's entries
+/// from those variables (restoring host defaults for the XDG dirs, which
+/// the sandbox replaces wholesale rather than extends) so spawned commands
+/// behave the same as an unsandboxed install.
+pub(crate) fn sandbox_normalize_env(target: &mut impl EnvTarget) {
+    let Some(prefix) = sandbox_prefix() else { return };
+
+    for var in &["LD_LIBRARY_PATH", "GST_PLUGIN_PATH", "GIO_MODULE_DIR"] {
+        if let Ok(value) = std::env::var(var) {
+            match strip_sandbox_paths(&value, &prefix) {
+                Some(stripped) => target.set_env(var, &stripped),
+                None => target.remove_env(var),
+            }
+        }
+    }
+
+    target.set_env("XDG_DATA_DIRS", "/usr/local/share:/usr/share");
+    target.set_env("XDG_CONFIG_DIRS", "/etc/xdg");
+}
+
+/// Expose the host platform/arch to spawned scripts, mirroring Tauri's own
+/// `before*Command` hooks exposing `TAURI_*` vars - lets agent tooling
+/// branch on platform without re-deriving it from `uname`/`ver`.
+pub(crate) fn inject_platform_env(target: &mut impl EnvTarget) {
+    target.set_env("AGENTCOCKPIT_PLATFORM", std::env::consts::OS);
+    target.set_env("AGENTCOCKPIT_ARCH", std::env::consts::ARCH);
+    target.set_env("AGENTCOCKPIT_FAMILY", std::env::consts::FAMILY);
+    target.set_env(
+        "AGENTCOCKPIT_TARGET_TRIPLE",
+        &format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+    );
+}
+
+/// Build a `sh -c <cmd>` command with the environment variables every
+/// command execution path (blocking and streaming) needs: HOME/USER/SHELL,
+/// the extended PATH, and common locale/editor passthrough vars.
+fn build_shell_command(cmd: &str, cwd: &str) -> Command {
     let mut command = Command::new("sh");
-    command.arg("-c").arg(&cmd).current_dir(&cwd);
+    command.arg("-c").arg(cmd).current_dir(cwd);
 
     // Copy essential environment variables (learned from opcode project)
     if let Ok(home) = std::env::var("HOME") {
@@ -141,7 +547,22 @@ fn execute_command(cmd: String, cwd: String) -> Result<String, String> {
         }
     }
 
-    let output = command.output().map_err(|e| e.to_string())?;
+    if cfg!(target_os = "linux") {
+        sandbox_normalize_env(&mut command);
+    }
+
+    inject_platform_env(&mut command);
+
+    command
+}
+
+/// Execute a shell command with proper environment variables
+/// CRITICAL: macOS bundled apps have limited environment, so we explicitly
+/// set HOME, USER, SHELL, PATH (with NVM/Homebrew) for all commands.
+/// This fixes git, mcp, and other CLI tools not working in bundled app.
+#[tauri::command]
+fn execute_command(cmd: String, cwd: String) -> Result<String, String> {
+    let output = build_shell_command(&cmd, &cwd).output().map_err(|e| e.to_string())?;
 
     if output.status.success() {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
@@ -150,16 +571,204 @@ fn execute_command(cmd: String, cwd: String) -> Result<String, String> {
     }
 }
 
+/// Tracks commands spawned by `execute_command_stream`, alongside `PtyManager`'s
+/// own table, so both can be torn down the same way on app shutdown.
+///
+/// `pty::PtyManager`'s reaper only `waitpid()`s the pids it knows about (see
+/// the note on `pty::reap_children`), so it never steals the exit status of
+/// a command spawned here. This module still avoids a blocking `Child::wait()`
+/// in favor of non-blocking `try_wait()` polls, so the registry's mutex isn't
+/// held for the lifetime of the child - `cancel_command` needs to acquire it
+/// to kill a still-running one.
+#[derive(Default)]
+pub struct CommandRegistry {
+    children: Mutex<std::collections::HashMap<String, std::process::Child>>,
+    next_id: Mutex<u64>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn alloc_id(&self) -> String {
+        let mut next_id = self.next_id.lock();
+        *next_id += 1;
+        format!("cmd-{}", *next_id)
+    }
+
+    /// Kill every tracked command. Called from the `RunEvent::Exit` handler
+    /// so streaming processes don't outlive the app, just like PTYs.
+    pub fn kill_all(&self) {
+        for (id, child) in self.children.lock().iter_mut() {
+            if let Err(e) = child.kill() {
+                log::warn!("[CommandRegistry] Failed to kill command {}: {}", id, e);
+            }
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
+struct CommandOutputPayload {
+    id: String,
+    data: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct CommandExitPayload {
+    id: String,
+    code: Option<i32>,
+}
+
+/// Spawn a long-running command with piped stdout/stderr, streaming output
+/// back via `command://stdout` / `command://stderr` events and a final
+/// `command://exit` event, instead of blocking until completion like
+/// `execute_command`. Returns the command id used to address it via
+/// `cancel_command`.
+#[tauri::command]
+fn execute_command_stream(
+    app: AppHandle,
+    registry: tauri::State<'_, Arc<CommandRegistry>>,
+    cmd: String,
+    cwd: String,
+) -> Result<String, String> {
+    let mut command = build_shell_command(&cmd, &cwd);
+    command.stdout(std::process::Stdio::piped());
+    command.stderr(std::process::Stdio::piped());
+
+    let mut child = command.spawn().map_err(|e| e.to_string())?;
+    let id = registry.alloc_id();
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    if let Some(mut stdout) = stdout {
+        let app = app.clone();
+        let id = id.clone();
+        thread::spawn(move || stream_output(&mut stdout, &app, &id, "command://stdout"));
+    }
+    if let Some(mut stderr) = stderr {
+        let app = app.clone();
+        let id = id.clone();
+        thread::spawn(move || stream_output(&mut stderr, &app, &id, "command://stderr"));
+    }
+
+    registry.children.lock().insert(id.clone(), child);
+
+    let registry = registry.inner().clone();
+    let app_for_exit = app.clone();
+    let id_for_exit = id.clone();
+    thread::spawn(move || {
+        // Poll rather than block on `Child::wait()`, so the registry lock is
+        // released between polls and `cancel_command` can still reach the
+        // child - see the note on `CommandRegistry` above.
+        let code = loop {
+            let mut children = registry.children.lock();
+            let Some(child) = children.get_mut(&id_for_exit) else { break None };
+            match child.try_wait() {
+                Ok(Some(status)) => break status.code(),
+                Ok(None) => {
+                    drop(children);
+                    thread::sleep(std::time::Duration::from_millis(100));
+                }
+                Err(_) => break None,
+            }
+        };
+        registry.children.lock().remove(&id_for_exit);
+        let _ = app_for_exit.emit("command://exit", CommandExitPayload { id: id_for_exit, code });
+    });
+
+    Ok(id)
+}
+
+/// Decode as much of `pending` as forms complete UTF-8, leaving any trailing
+/// incomplete sequence in `pending` to be joined with the next chunk instead
+/// of being replaced with `\u{FFFD}`. Only sequences that are complete but
+/// malformed are treated as truly invalid and replaced. Same approach as
+/// `ClaudeParser::decode_chunk` and the PTY reactor's pending-byte carryover,
+/// applied here since a raw child process's stdout/stderr chunks can split a
+/// multi-byte character across reads just the same as a PTY's.
+fn decode_utf8_incremental(pending: &mut Vec<u8>) -> String {
+    let mut out = String::new();
+    let mut rest: &[u8] = pending;
+
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(s) => {
+                out.push_str(s);
+                rest = &[];
+                break;
+            }
+            Err(e) => {
+                let valid_up_to = e.valid_up_to();
+                out.push_str(std::str::from_utf8(&rest[..valid_up_to]).unwrap());
+
+                match e.error_len() {
+                    Some(len) => {
+                        out.push('\u{FFFD}');
+                        rest = &rest[valid_up_to + len..];
+                    }
+                    None => {
+                        rest = &rest[valid_up_to..];
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    let leftover = rest.to_vec();
+    *pending = leftover;
+    out
+}
+
+/// Read chunks from `stream` until EOF, emitting each as `event`.
+fn stream_output(stream: &mut impl std::io::Read, app: &AppHandle, id: &str, event: &str) {
+    let mut buf = [0u8; 8192];
+    let mut pending: Vec<u8> = Vec::new();
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                pending.extend_from_slice(&buf[..n]);
+                let data = decode_utf8_incremental(&mut pending);
+                if !data.is_empty() {
+                    let _ = app.emit(event, CommandOutputPayload { id: id.to_string(), data });
+                }
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+/// Kill a command started by `execute_command_stream` by id.
+#[tauri::command]
+fn cancel_command(registry: tauri::State<'_, Arc<CommandRegistry>>, id: String) -> Result<(), String> {
+    if let Some(child) = registry.children.lock().get_mut(&id) {
+        child.kill().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let pty_manager = Arc::new(Mutex::new(PtyManager::new()));
     let pty_manager_for_shutdown = pty_manager.clone();
+    let browser_state = Arc::new(Mutex::new(BrowserState::new()));
+    let download_state = Arc::new(DownloadState::new());
+    let now_playing = Arc::new(NowPlaying::new());
+    let command_registry = Arc::new(CommandRegistry::new());
+    let command_registry_for_shutdown = command_registry.clone();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
         .manage(pty_manager)
+        .manage(browser_state)
+        .manage(download_state)
+        .manage(now_playing)
+        .manage(command_registry)
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -168,14 +777,42 @@ pub fn run() {
                         .build(),
                 )?;
             }
+            let now_playing = app.state::<Arc<NowPlaying>>().inner().clone();
+            now_playing.attach(&app.handle());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             execute_command,
+            detect_environment,
             pty::pty_spawn,
             pty::pty_write,
             pty::pty_resize,
             pty::pty_close,
+            pty::pty_signal,
+            pty::pty_foreground_process,
+            pty::pty_set_mode,
+            browser::browser_create,
+            browser::browser_close,
+            browser::browser_close_all,
+            browser::browser_navigate,
+            browser::browser_set_position,
+            browser::browser_show,
+            browser::browser_hide,
+            browser::browser_hide_all,
+            browser::browser_exists,
+            browser::browser_get_tabs,
+            browser::browser_clear_session,
+            browser::browser_url_report,
+            browser::media_state_report,
+            browser::media_send_command,
+            browser::media_sync_group,
+            browser::media_sync_stop,
+            media::media_resolve_url,
+            downloads::media_download_start,
+            downloads::media_download_cancel,
+            downloads::media_download_list,
+            execute_command_stream,
+            cancel_command,
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
@@ -185,6 +822,7 @@ pub fn run() {
                 log::info!("App shutting down - cleaning up PTY processes");
                 let mut manager = pty_manager_for_shutdown.lock();
                 manager.close_all();
+                command_registry_for_shutdown.kill_all();
             }
         });
 }
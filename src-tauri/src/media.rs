@@ -0,0 +1,94 @@
+use tauri::Url;
+
+/// What a resolved URL points at. Kept separate from a bare "is this a
+/// video" bool because music album watch URLs and artist channels need to
+/// be told apart from regular videos/playlists - see `resolve_url`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UrlTarget {
+    Video { id: String },
+    Playlist { id: String },
+    Album { id: String },
+    Channel { id: String },
+}
+
+/// Canonical metadata for a resolved media URL. The injected DOM scraper in
+/// `browser.rs` is best-effort and breaks whenever YouTube reshuffles its
+/// markup; this is the server-side fallback/cross-check.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MediaEntity {
+    pub target: UrlTarget,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub artwork_url: Option<String>,
+}
+
+/// Classify a URL reported via `browser_url_report` into a [`UrlTarget`],
+/// without fetching anything - just parsing the URL shape the way YouTube
+/// and YouTube Music lay out their query params.
+///
+/// `scraped_title` is the title the injected DOM scraper in `browser.rs`
+/// already read off the page; this has no network access of its own (no
+/// oEmbed/watch-page fetch), so it's threaded straight into the entity as
+/// the best title available rather than leaving it `None`. `artist`/`album`/
+/// `artwork_url` remain unresolved until a real metadata fetch is added.
+///
+/// Returns `None` for URLs that aren't a YouTube/YouTube Music watch,
+/// playlist, album, or channel page (e.g. the homepage, search results).
+pub fn resolve_url(url: &str, scraped_title: Option<&str>) -> Option<MediaEntity> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    if !host.ends_with("youtube.com") && !host.ends_with("youtu.be") {
+        return None;
+    }
+
+    let is_music = host.starts_with("music.");
+    let params: std::collections::HashMap<_, _> = parsed.query_pairs().into_owned().collect();
+    let path = parsed.path();
+
+    let target = if host.ends_with("youtu.be") {
+        UrlTarget::Video { id: path.trim_start_matches('/').to_string() }
+    } else if let Some(video_id) = params.get("v") {
+        // Music "album" watch URLs are regular /watch?v=...&list=OLAK5uy_...
+        // pages - the OLAK5uy prefix on `list` is YouTube Music's marker for
+        // an auto-generated album playlist, so treat those as an Album
+        // rather than a bare Video.
+        match params.get("list") {
+            Some(list_id) if is_music && list_id.starts_with("OLAK5uy") => {
+                UrlTarget::Album { id: list_id.clone() }
+            }
+            _ => UrlTarget::Video { id: video_id.clone() },
+        }
+    } else if path.starts_with("/playlist") {
+        let list_id = params.get("list")?.clone();
+        if is_music && list_id.starts_with("OLAK5uy") {
+            UrlTarget::Album { id: list_id }
+        } else {
+            UrlTarget::Playlist { id: list_id }
+        }
+    } else if let Some(channel_id) = path.strip_prefix("/channel/") {
+        UrlTarget::Channel { id: channel_id.trim_end_matches('/').to_string() }
+    } else if let Some(handle) = path.strip_prefix("/@") {
+        // Artist/channel "handles" (e.g. music.youtube.com/@some-artist) can
+        // point at a channel assembled from search results rather than a
+        // real uploads channel - still a Channel as far as the UI cares.
+        UrlTarget::Channel { id: format!("@{}", handle.trim_end_matches('/')) }
+    } else {
+        return None;
+    };
+
+    Some(MediaEntity {
+        target,
+        title: scraped_title.map(str::to_string),
+        artist: None,
+        album: None,
+        artwork_url: None,
+    })
+}
+
+/// Resolve the URL currently reported for a tab into a [`MediaEntity`].
+#[tauri::command]
+pub fn media_resolve_url(url: String, scraped_title: Option<String>) -> Result<MediaEntity, String> {
+    resolve_url(&url, scraped_title.as_deref()).ok_or_else(|| format!("Not a recognized media URL: {}", url))
+}
@@ -0,0 +1,122 @@
+use crate::browser::MediaStatePayload;
+use parking_lot::Mutex;
+use souvlaki::{MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, PlatformConfig};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
+/// Wraps the OS now-playing widget / hardware media key integration
+/// (`souvlaki`) so hardware play/pause/next/prev/seek map onto whichever tab
+/// last reported media state. `souvlaki::MediaControls` isn't `Send`-safe to
+/// share across threads, so it's kept behind a `Mutex` the same way
+/// `pty::PtyManager` guards its non-thread-safe PTY handles.
+pub struct NowPlaying {
+    controls: Mutex<Option<MediaControls>>,
+    /// Tab that should receive hardware media key events - the tab that most
+    /// recently reported a state change via `media_state_report`.
+    active_tab: Mutex<Option<String>>,
+}
+
+/// Hardware/OS media control event re-emitted to the frontend, which routes
+/// it through the same path as a `media_send_command` call for `active_tab`.
+#[derive(Clone, serde::Serialize)]
+pub struct NowPlayingEventPayload {
+    pub tab_id: String,
+    pub kind: String,
+    pub value: Option<f64>,
+}
+
+impl NowPlaying {
+    pub fn new() -> Self {
+        Self {
+            controls: Mutex::new(None),
+            active_tab: Mutex::new(None),
+        }
+    }
+
+    /// Attach to the OS media session. Called once from the app's setup hook.
+    /// No window handle is passed (`hwnd` is `None`), so this only attaches
+    /// where the OS media session does not need one.
+    pub fn attach(self: &Arc<Self>, app: &AppHandle) {
+        let config = PlatformConfig {
+            dbus_name: "agentcockpit",
+            display_name: "Agent Cockpit",
+            hwnd: None,
+        };
+
+        let mut controls = match MediaControls::new(config) {
+            Ok(controls) => controls,
+            Err(e) => {
+                log::warn!("[NowPlaying] OS media session unavailable: {:?}", e);
+                return;
+            }
+        };
+
+        let app_for_events = app.clone();
+        let this = self.clone();
+        let attach_result = controls.attach(move |event: MediaControlEvent| {
+            let Some(tab_id) = this.active_tab.lock().clone() else { return };
+            let (kind, value) = match event {
+                MediaControlEvent::Play => ("play", None),
+                MediaControlEvent::Pause => ("pause", None),
+                MediaControlEvent::Toggle => ("toggle", None),
+                MediaControlEvent::Next => ("next", None),
+                MediaControlEvent::Previous => ("prev", None),
+                MediaControlEvent::Seek(direction) => (
+                    "seek_relative",
+                    Some(match direction {
+                        souvlaki::SeekDirection::Forward => 10.0,
+                        souvlaki::SeekDirection::Backward => -10.0,
+                    }),
+                ),
+                MediaControlEvent::SetPosition(souvlaki::MediaPosition(position)) => {
+                    ("seek", Some(position.as_secs_f64()))
+                }
+                _ => return,
+            };
+            let _ = app_for_events.emit(
+                "now-playing-event",
+                NowPlayingEventPayload { tab_id, kind: kind.to_string(), value },
+            );
+        });
+
+        if let Err(e) = attach_result {
+            log::warn!("[NowPlaying] Failed to attach media control handler: {:?}", e);
+            return;
+        }
+
+        *self.controls.lock() = Some(controls);
+    }
+
+    /// Push a tab's reported media state into the OS now-playing widget.
+    /// Only the most recently active tab drives it - there's one OS media
+    /// session, not one per browser tab.
+    pub fn update(&self, payload: &MediaStatePayload) {
+        *self.active_tab.lock() = Some(payload.tab_id.clone());
+
+        let mut controls = self.controls.lock();
+        let Some(controls) = controls.as_mut() else { return };
+
+        let _ = controls.set_metadata(MediaMetadata {
+            title: Some(&payload.title),
+            artist: payload.artist.as_deref(),
+            album: payload.album.as_deref(),
+            cover_url: payload.artwork_url.as_deref(),
+            duration: Some(std::time::Duration::from_secs_f64(payload.duration.max(0.0))),
+        });
+
+        let playback = if payload.is_playing {
+            MediaPlayback::Playing {
+                progress: Some(souvlaki::MediaPosition(std::time::Duration::from_secs_f64(
+                    payload.current_time.max(0.0),
+                ))),
+            }
+        } else {
+            MediaPlayback::Paused {
+                progress: Some(souvlaki::MediaPosition(std::time::Duration::from_secs_f64(
+                    payload.current_time.max(0.0),
+                ))),
+            }
+        };
+        let _ = controls.set_playback(playback);
+    }
+}
@@ -1,11 +1,28 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use parking_lot::Mutex;
-use portable_pty::{native_pty_system, CommandBuilder, PtySize, MasterPty, Child};
+use portable_pty::{native_pty_system, CommandBuilder, PtySize, MasterPty, SlavePty, Child};
 use std::collections::HashMap;
 use std::io::{Read, Write};
-use std::sync::Arc;
+use std::sync::{Arc, Once};
 use std::thread;
 use tauri::{AppHandle, Emitter, State};
 
+#[cfg(unix)]
+use polling::{Event, Events, PollMode, Poller};
+#[cfg(unix)]
+use portable_pty::unix::{MasterPtyExt, SlavePtyExt};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+// IUTF8 is only taken from libc on Linux/Android; on other Unixes (macOS,
+// BSD) the flag is defined as 0 here, so toggling it there is a no-op.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const IUTF8: libc::tcflag_t = libc::IUTF8;
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+const IUTF8: libc::tcflag_t = 0;
+
 /// Find the last valid UTF-8 character boundary in a byte slice.
 /// Returns the number of bytes that form complete UTF-8 characters.
 fn find_utf8_boundary(bytes: &[u8]) -> usize {
@@ -55,22 +72,102 @@ fn find_utf8_boundary(bytes: &[u8]) -> usize {
     }
 }
 
+/// The process currently holding the foreground of a PTY's process group,
+/// returned by [`pty_foreground_process`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ForegroundProcess {
+    pub pid: i32,
+    pub name: String,
+    pub cmdline: String,
+}
+
+/// How a PTY's child process went away, reported on `pty-exit-{id}`.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PtyExit {
+    /// Process called `exit()` (or returned from `main`) with this code.
+    Exited { code: i32 },
+    /// Process was terminated by a signal (Unix only).
+    Signaled { signal: i32 },
+}
+
+/// Write request for [`pty_write`], carrying exact bytes rather than a
+/// lossy UTF-8 `String` so pasted binary/sixel data survives the IPC hop.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct PtyInput {
+    pub id: u32,
+    pub data: Vec<u8>,
+}
+
+/// Resize request for [`pty_resize`]. `pixel_width`/`pixel_height` are the
+/// cell-pixel dimensions the frontend knows from its font metrics; see
+/// `PtySize` in portable_pty for how they're used.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct PtyResize {
+    pub id: u32,
+    pub cols: u16,
+    pub rows: u16,
+    #[serde(default)]
+    pub pixel_width: u16,
+    #[serde(default)]
+    pub pixel_height: u16,
+}
+
+/// Line discipline toggles for [`pty_set_mode`]. `preset` is applied first
+/// (a shorthand for the common "raw" and "cooked" combinations), then any
+/// individually set field overrides it - so callers can start from a preset
+/// and tweak one flag without re-specifying the rest.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct TermiosMode {
+    /// `"raw"` (no echo, no canonical editing, no flow control) or
+    /// `"cooked"` (the usual shell defaults).
+    pub preset: Option<String>,
+    pub echo: Option<bool>,
+    pub canonical: Option<bool>,
+    pub flow_control: Option<bool>,
+    pub utf8: Option<bool>,
+}
+
 pub struct PtyInstance {
     master: Box<dyn MasterPty + Send>,
+    // Kept alive (but otherwise unused) so we retain an fd to re-apply
+    // termios settings after the child has started.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    slave: Box<dyn SlavePty + Send>,
+    reader: Box<dyn Read + Send>,
     writer: Box<dyn Write + Send>,
     child: Box<dyn Child + Send>,  // Track child process for cleanup
+    pending: Vec<u8>,              // Bytes read but not yet emitted (incomplete UTF-8 tail)
+    /// When set, every chunk is also emitted losslessly (base64) on
+    /// `pty-raw-{id}` for sessions that need byte-accurate rendering
+    /// (sixel/kitty/iTerm image protocols, piped binaries).
+    raw_mode: bool,
 }
 
 pub struct PtyManager {
-    instances: HashMap<u32, PtyInstance>,
+    instances: Arc<Mutex<HashMap<u32, PtyInstance>>>,
     next_id: u32,
+    /// Maps a child's OS pid back to its PTY id so the reactor can route a
+    /// reaped SIGCHLD to the right `pty-exit-{id}` event.
+    pid_to_id: Arc<Mutex<HashMap<u32, u32>>>,
+    /// The single I/O reactor shared by every PTY (Unix only - see
+    /// `spawn_reactor`). Created eagerly so `spawn()` can register fds with
+    /// it immediately; the background thread itself is started lazily on
+    /// the first spawn, once an `AppHandle` is available.
+    #[cfg(unix)]
+    poller: Arc<Poller>,
+    reactor_started: Once,
 }
 
 impl PtyManager {
     pub fn new() -> Self {
         PtyManager {
-            instances: HashMap::new(),
+            instances: Arc::new(Mutex::new(HashMap::new())),
             next_id: 1,
+            pid_to_id: Arc::new(Mutex::new(HashMap::new())),
+            #[cfg(unix)]
+            poller: Arc::new(Poller::new().expect("failed to create PTY I/O reactor")),
+            reactor_started: Once::new(),
         }
     }
 
@@ -80,19 +177,37 @@ impl PtyManager {
         cwd: &str,
         cols: u16,
         rows: u16,
+        pixel_width: u16,
+        pixel_height: u16,
+        raw_mode: bool,
+        initial_mode: Option<TermiosMode>,
         app: AppHandle,
     ) -> Result<u32, String> {
+        #[cfg(unix)]
+        self.reactor_started.call_once(|| {
+            Self::spawn_reactor(
+                self.instances.clone(),
+                self.pid_to_id.clone(),
+                self.poller.clone(),
+                app.clone(),
+            );
+        });
         let pty_system = native_pty_system();
 
         let pair = pty_system
             .openpty(PtySize {
                 rows,
                 cols,
-                pixel_width: 0,
-                pixel_height: 0,
+                pixel_width,
+                pixel_height,
             })
             .map_err(|e| e.to_string())?;
 
+        #[cfg(unix)]
+        if let Some(mode) = &initial_mode {
+            Self::apply_termios(pair.slave.as_raw_fd(), mode)?;
+        }
+
         let mut cmd_builder = CommandBuilder::new(cmd);
         cmd_builder.cwd(cwd);
 
@@ -100,15 +215,15 @@ impl PtyManager {
         cmd_builder.env("TERM", "xterm-256color");
         cmd_builder.env("COLORTERM", "truecolor");
 
-        // Ensure common binary paths are in PATH for bundled app
-        // The bundled macOS app doesn't inherit shell PATH, so we add common locations
-        let current_path = std::env::var("PATH").unwrap_or_default();
-        let extended_path = format!(
-            "/usr/local/bin:/opt/homebrew/bin:/usr/bin:/bin:{}/.local/bin:{}",
-            std::env::var("HOME").unwrap_or_default(),
-            current_path
-        );
-        cmd_builder.env("PATH", &extended_path);
+        // Use the same cross-platform, deduped, version-manager-aware PATH
+        // that `execute_command`/`execute_command_stream` build, so PTY
+        // sessions see the same node/git/etc. as one-off commands.
+        cmd_builder.env("PATH", crate::build_extended_path());
+
+        if cfg!(target_os = "linux") {
+            crate::sandbox_normalize_env(&mut cmd_builder);
+        }
+        crate::inject_platform_env(&mut cmd_builder);
 
         // Note: Process group setup (setsid) is handled automatically by portable_pty
         // when spawning the command. The slave PTY makes the child process a session
@@ -121,109 +236,481 @@ impl PtyManager {
         self.next_id += 1;
 
         // Get reader and writer
-        let mut reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+        let reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
         let writer = pair.master.take_writer().map_err(|e| e.to_string())?;
 
-        // Spawn thread to read PTY output and emit events
-        let pty_id = id;
+        if let Some(pid) = child.process_id() {
+            self.pid_to_id.lock().insert(pid, id);
+        }
+
+        #[cfg(unix)]
+        {
+            let fd = pair.master.as_raw_fd();
+            // The reactor polls this fd from a single shared thread, so it must
+            // never block that thread waiting for more data than is available.
+            unsafe {
+                let flags = libc::fcntl(fd, libc::F_GETFL, 0);
+                if flags >= 0 {
+                    libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+                }
+            }
+            unsafe {
+                self.poller
+                    .add_with_mode(fd, Event::readable(id as usize), PollMode::Oneshot)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        self.instances.lock().insert(id, PtyInstance {
+            master: pair.master,
+            slave: pair.slave,
+            reader,
+            writer,
+            child,
+            pending: Vec::new(),
+            raw_mode,
+        });
+
+        #[cfg(windows)]
+        Self::spawn_windows_reader(id, self.instances.clone(), app);
+
+        Ok(id)
+    }
+
+    /// Single background reactor shared by every PTY session (Unix). Polls
+    /// all master fds plus a self-pipe fed by the SIGCHLD handler, instead
+    /// of parking one blocking reader thread per PTY.
+    #[cfg(unix)]
+    fn spawn_reactor(
+        instances: Arc<Mutex<HashMap<u32, PtyInstance>>>,
+        pid_to_id: Arc<Mutex<HashMap<u32, u32>>>,
+        poller: Arc<Poller>,
+        app: AppHandle,
+    ) {
         thread::spawn(move || {
-            let mut buf = [0u8; 4096];
-            let mut pending: Vec<u8> = Vec::new();
+            const SIGNAL_KEY: usize = usize::MAX;
+
+            let (mut sig_read, sig_write) = match UnixStream::pair() {
+                Ok(pair) => pair,
+                Err(e) => {
+                    log::error!("[pty] failed to create signal self-pipe: {e}");
+                    return;
+                }
+            };
+            if let Err(e) = sig_read.set_nonblocking(true) {
+                log::error!("[pty] failed to set signal pipe non-blocking: {e}");
+                return;
+            }
+            if let Err(e) = signal_hook::low_level::pipe::register(libc::SIGCHLD, sig_write) {
+                log::error!("[pty] failed to register SIGCHLD handler: {e}");
+                return;
+            }
+
+            unsafe {
+                if let Err(e) = poller.add(&sig_read, Event::readable(SIGNAL_KEY)) {
+                    log::error!("[pty] failed to register signal pipe: {e}");
+                    return;
+                }
+            }
+
+            let mut events = Events::new();
 
             loop {
-                match reader.read(&mut buf) {
-                    Ok(0) => {
-                        // PTY closed - emit any remaining data
-                        if !pending.is_empty() {
-                            let data = String::from_utf8_lossy(&pending).to_string();
-                            let _ = app.emit(&format!("pty-output-{}", pty_id), data);
+                events.clear();
+                if let Err(e) = poller.wait(&mut events, None) {
+                    if e.kind() == std::io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    log::error!("[pty] reactor poll error: {e}");
+                    continue;
+                }
+
+                for ev in events.iter() {
+                    if ev.key == SIGNAL_KEY {
+                        // Drain the pipe, then reap every child that has exited so
+                        // far. waitpid(-1, WNOHANG) can coalesce several signals.
+                        let mut drain = [0u8; 64];
+                        while sig_read.read(&mut drain).map(|n| n > 0).unwrap_or(false) {}
+                        Self::reap_children(&instances, &pid_to_id, &poller, &app);
+                        let _ = poller.modify(&sig_read, Event::readable(SIGNAL_KEY));
+                        continue;
+                    }
+
+                    let id = ev.key as u32;
+                    let mut map = instances.lock();
+                    let Some(instance) = map.get_mut(&id) else {
+                        continue;
+                    };
+
+                    let mut buf = [0u8; 4096];
+                    match instance.reader.read(&mut buf) {
+                        Ok(0) => {
+                            // EOF on the PTY can arrive slightly before SIGCHLD;
+                            // leave removal/exit-code reporting to reap_children
+                            // so the frontend always gets a real exit status.
+                        }
+                        Ok(n) => {
+                            if instance.raw_mode {
+                                let _ = app.emit(&format!("pty-raw-{}", id), BASE64.encode(&buf[..n]));
+                            }
+                            instance.pending.extend_from_slice(&buf[..n]);
+                            let valid_up_to = find_utf8_boundary(&instance.pending);
+                            if valid_up_to > 0 {
+                                let complete =
+                                    String::from_utf8_lossy(&instance.pending[..valid_up_to])
+                                        .to_string();
+                                let _ = app.emit(&format!("pty-output-{}", id), complete);
+                                instance.pending.drain(..valid_up_to);
+                            }
+                            let fd = instance.master.as_raw_fd();
+                            let _ = poller.modify(fd, Event::readable(id as usize));
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                            let fd = instance.master.as_raw_fd();
+                            let _ = poller.modify(fd, Event::readable(id as usize));
+                        }
+                        Err(_) => {
+                            // Real read error - stop polling this fd, exit
+                            // reporting is still owned by reap_children.
                         }
-                        let _ = app.emit(&format!("pty-close-{}", pty_id), ());
-                        break;
                     }
-                    Ok(n) => {
-                        // Append new data to pending buffer
-                        pending.extend_from_slice(&buf[..n]);
+                }
+            }
+        });
+    }
 
-                        // Find the last valid UTF-8 boundary
-                        let valid_up_to = find_utf8_boundary(&pending);
+    /// Reap every PTY child that has exited and emit `pty-exit-{id}`.
+    ///
+    /// Deliberately does *not* use a blind `waitpid(-1, WNOHANG)`: that reaps
+    /// the exit status of *any* child of this process, including ones other
+    /// subsystems spawned (e.g. `execute_command_stream`'s `CommandRegistry`,
+    /// which polls its own children with `try_wait()`), stealing the status
+    /// before they ever see it. Instead, only `waitpid()` the pids this
+    /// manager actually knows about from `pid_to_id`, so other subsystems'
+    /// children are left for them to reap.
+    #[cfg(unix)]
+    fn reap_children(
+        instances: &Arc<Mutex<HashMap<u32, PtyInstance>>>,
+        pid_to_id: &Arc<Mutex<HashMap<u32, u32>>>,
+        poller: &Arc<Poller>,
+        app: &AppHandle,
+    ) {
+        let known_pids: Vec<u32> = pid_to_id.lock().keys().copied().collect();
+
+        for pid in known_pids {
+            let mut status: libc::c_int = 0;
+            let result = unsafe { libc::waitpid(pid as i32, &mut status, libc::WNOHANG) };
+            if result <= 0 {
+                continue;
+            }
 
-                        if valid_up_to > 0 {
-                            // Convert and emit only complete UTF-8 characters
-                            let complete = String::from_utf8_lossy(&pending[..valid_up_to]).to_string();
-                            let _ = app.emit(&format!("pty-output-{}", pty_id), complete);
+            let Some(id) = pid_to_id.lock().remove(&pid) else {
+                continue;
+            };
 
-                            // Keep incomplete bytes for next iteration
-                            pending.drain(..valid_up_to);
-                        }
+            let exit = if libc::WIFEXITED(status) {
+                PtyExit::Exited { code: libc::WEXITSTATUS(status) }
+            } else if libc::WIFSIGNALED(status) {
+                PtyExit::Signaled { signal: libc::WTERMSIG(status) }
+            } else {
+                continue;
+            };
+
+            // Flush anything still pending before tearing the session down.
+            if let Some(mut instance) = instances.lock().remove(&id) {
+                if !instance.pending.is_empty() {
+                    let data = String::from_utf8_lossy(&instance.pending).to_string();
+                    let _ = app.emit(&format!("pty-output-{}", id), data);
+                    instance.pending.clear();
+                }
+                // The master fd must be delete()d from the poller before it's
+                // dropped here - `polling` documents dropping a still-registered
+                // source as unsound.
+                let fd = instance.master.as_raw_fd();
+                let _ = poller.delete(fd);
+            }
+
+            let _ = app.emit(&format!("pty-exit-{}", id), exit);
+        }
+    }
+
+    /// Windows has no fd-pollable equivalent of a ConPTY handle, so each
+    /// session keeps its own blocking reader thread; what this removes on
+    /// Windows is the *silent* exit - a dedicated wait thread now reports
+    /// the real exit status instead of the child vanishing unnoticed.
+    #[cfg(windows)]
+    fn spawn_windows_reader(id: u32, instances: Arc<Mutex<HashMap<u32, PtyInstance>>>, app: AppHandle) {
+        let instances_for_exit = instances.clone();
+        let app_for_exit = app.clone();
+        thread::spawn(move || {
+            loop {
+                let mut buf = [0u8; 4096];
+                let read_result = {
+                    let mut map = instances.lock();
+                    match map.get_mut(&id) {
+                        Some(instance) => instance.reader.read(&mut buf),
+                        None => break,
                     }
-                    Err(_) => {
-                        let _ = app.emit(&format!("pty-close-{}", pty_id), ());
-                        break;
+                };
+
+                match read_result {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        let mut map = instances.lock();
+                        let Some(instance) = map.get_mut(&id) else { break };
+                        if instance.raw_mode {
+                            let _ = app.emit(&format!("pty-raw-{}", id), BASE64.encode(&buf[..n]));
+                        }
+                        instance.pending.extend_from_slice(&buf[..n]);
+                        let valid_up_to = find_utf8_boundary(&instance.pending);
+                        if valid_up_to > 0 {
+                            let complete =
+                                String::from_utf8_lossy(&instance.pending[..valid_up_to]).to_string();
+                            drop(map);
+                            let _ = app.emit(&format!("pty-output-{}", id), complete);
+                            let mut map = instances.lock();
+                            if let Some(instance) = map.get_mut(&id) {
+                                instance.pending.drain(..valid_up_to);
+                            }
+                        }
                     }
+                    Err(_) => break,
                 }
             }
         });
 
-        self.instances.insert(id, PtyInstance {
-            master: pair.master,
-            writer,
-            child,
+        thread::spawn(move || {
+            let exit_status = {
+                let mut map = instances_for_exit.lock();
+                map.get_mut(&id).and_then(|instance| instance.child.wait().ok())
+            };
+
+            let exit = match exit_status {
+                Some(status) => PtyExit::Exited { code: status.exit_code() as i32 },
+                None => return,
+            };
+
+            instances_for_exit.lock().remove(&id);
+            let _ = app_for_exit.emit(&format!("pty-exit-{}", id), exit);
         });
+    }
 
-        Ok(id)
+    /// Report the process currently sitting in the foreground of a PTY's
+    /// process group (e.g. a shell prompt vs. `claude`/`vim`/`git` actively
+    /// running), so the frontend can badge a terminal tab with its live
+    /// command instead of scraping terminal output for it.
+    pub fn foreground_process(&self, id: u32) -> Result<Option<ForegroundProcess>, String> {
+        let instances = self.instances.lock();
+        let instance = instances.get(&id).ok_or("PTY not found")?;
+
+        #[cfg(unix)]
+        {
+            let fd = instance.master.as_raw_fd();
+            let pgid = unsafe { libc::tcgetpgrp(fd) };
+            if pgid <= 0 {
+                return Ok(None);
+            }
+
+            let mut system = sysinfo::System::new();
+            let sys_pid = sysinfo::Pid::from_u32(pgid as u32);
+            system.refresh_process_specifics(sys_pid, sysinfo::ProcessRefreshKind::everything());
+
+            Ok(system.process(sys_pid).map(|process| ForegroundProcess {
+                pid: pgid,
+                name: process.name().to_string(),
+                cmdline: process.cmd().join(" "),
+            }))
+        }
+
+        #[cfg(windows)]
+        {
+            // No direct equivalent of tcgetpgrp on Windows consoles; callers
+            // should fall back to assuming the session is at its shell.
+            Ok(None)
+        }
     }
 
-    pub fn write(&mut self, id: u32, data: &str) -> Result<(), String> {
-        let instance = self.instances.get_mut(&id).ok_or("PTY not found")?;
-        instance.writer.write_all(data.as_bytes()).map_err(|e| e.to_string())?;
+    /// Toggle line discipline (echo, canonical editing, flow control, UTF-8
+    /// input) for a running session, e.g. flipping into raw mode before
+    /// launching a full-screen TUI and back to cooked mode afterward.
+    pub fn set_mode(&self, id: u32, mode: &TermiosMode) -> Result<(), String> {
+        #[cfg(unix)]
+        {
+            let instances = self.instances.lock();
+            let instance = instances.get(&id).ok_or("PTY not found")?;
+            Self::apply_termios(instance.slave.as_raw_fd(), mode)
+        }
+
+        #[cfg(windows)]
+        {
+            let _ = (id, mode);
+            Err("Line discipline control is not supported on Windows".to_string())
+        }
+    }
+
+    #[cfg(unix)]
+    fn apply_termios(fd: RawFd, mode: &TermiosMode) -> Result<(), String> {
+        unsafe {
+            let mut term: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(fd, &mut term) != 0 {
+                return Err(std::io::Error::last_os_error().to_string());
+            }
+
+            match mode.preset.as_deref() {
+                Some("raw") => {
+                    term.c_lflag &= !(libc::ECHO | libc::ICANON);
+                    term.c_iflag &= !(libc::IXON | libc::IXOFF);
+                    term.c_iflag |= IUTF8;
+                }
+                Some("cooked") => {
+                    term.c_lflag |= libc::ECHO | libc::ICANON;
+                    term.c_iflag |= libc::IXON | libc::IXOFF | IUTF8;
+                }
+                Some(other) => return Err(format!("Unknown termios preset: {}", other)),
+                None => {}
+            }
+
+            if let Some(echo) = mode.echo {
+                Self::set_flag(&mut term.c_lflag, libc::ECHO, echo);
+            }
+            if let Some(canonical) = mode.canonical {
+                Self::set_flag(&mut term.c_lflag, libc::ICANON, canonical);
+            }
+            if let Some(flow_control) = mode.flow_control {
+                Self::set_flag(&mut term.c_iflag, libc::IXON | libc::IXOFF, flow_control);
+            }
+            if let Some(utf8) = mode.utf8 {
+                if IUTF8 != 0 {
+                    Self::set_flag(&mut term.c_iflag, IUTF8, utf8);
+                }
+            }
+
+            if libc::tcsetattr(fd, libc::TCSANOW, &term) != 0 {
+                return Err(std::io::Error::last_os_error().to_string());
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn set_flag(field: &mut libc::tcflag_t, bits: libc::tcflag_t, enabled: bool) {
+        if enabled {
+            *field |= bits;
+        } else {
+            *field &= !bits;
+        }
+    }
+
+    pub fn write(&mut self, id: u32, data: &[u8]) -> Result<(), String> {
+        let mut instances = self.instances.lock();
+        let instance = instances.get_mut(&id).ok_or("PTY not found")?;
+        instance.writer.write_all(data).map_err(|e| e.to_string())?;
         instance.writer.flush().map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    pub fn resize(&mut self, id: u32, cols: u16, rows: u16) -> Result<(), String> {
-        let instance = self.instances.get_mut(&id).ok_or("PTY not found")?;
+    pub fn resize(&mut self, id: u32, cols: u16, rows: u16, pixel_width: u16, pixel_height: u16) -> Result<(), String> {
+        let instances = self.instances.lock();
+        let instance = instances.get(&id).ok_or("PTY not found")?;
         instance.master.resize(PtySize {
             rows,
             cols,
-            pixel_width: 0,
-            pixel_height: 0,
+            pixel_width,
+            pixel_height,
         }).map_err(|e| e.to_string())
     }
 
-    pub fn close(&mut self, id: u32) -> Result<(), String> {
-        if let Some(mut instance) = self.instances.remove(&id) {
-            // Kill process group (shell + all descendants like Claude)
-            #[cfg(unix)]
-            {
-                if let Some(pid) = instance.child.process_id() {
-                    unsafe {
-                        // Send SIGTERM first for graceful shutdown
-                        libc::kill(-(pid as i32), libc::SIGTERM);
-
-                        // Wait longer for graceful shutdown (500ms instead of 100ms)
-                        // Gives Claude time to cleanup properly
-                        std::thread::sleep(std::time::Duration::from_millis(500));
-
-                        // Check if process still exists before SIGKILL
-                        let still_alive = libc::kill(-(pid as i32), 0) == 0;
-
-                        if still_alive {
-                            // SIGKILL if still running
-                            libc::kill(-(pid as i32), libc::SIGKILL);
-                            std::thread::sleep(std::time::Duration::from_millis(100));
-                        }
-                    }
+    /// Deliver a signal (by name or number, e.g. `"SIGINT"`/`"2"`) to a
+    /// session's whole process group, so the frontend can implement a real
+    /// "Stop" button or job control instead of relying on `\x03` bytes (which
+    /// don't reach anything once the child has put the tty in raw mode).
+    pub fn signal(&self, id: u32, signal: &str) -> Result<(), String> {
+        #[cfg(unix)]
+        {
+            let instances = self.instances.lock();
+            let instance = instances.get(&id).ok_or("PTY not found")?;
+            let pid = instance.child.process_id().ok_or("Process has no pid")?;
+            let sig = Self::parse_unix_signal(signal)?;
+
+            let result = unsafe { libc::kill(-(pid as i32), sig) };
+            if result != 0 {
+                return Err(std::io::Error::last_os_error().to_string());
+            }
+            Ok(())
+        }
+
+        #[cfg(windows)]
+        {
+            let mut instances = self.instances.lock();
+            let instance = instances.get_mut(&id).ok_or("PTY not found")?;
+            match signal.to_uppercase().as_str() {
+                "SIGKILL" | "KILL" | "9" | "SIGTERM" | "TERM" | "15" | "SIGHUP" | "HUP" | "1" => {
+                    instance.child.kill().map_err(|e| e.to_string())
                 }
+                other => Err(format!(
+                    "Signal '{}' has no Windows equivalent - only a hard kill is supported",
+                    other
+                )),
             }
+        }
+    }
 
-            #[cfg(windows)]
-            {
-                let _ = instance.child.kill();
+    #[cfg(unix)]
+    fn parse_unix_signal(signal: &str) -> Result<i32, String> {
+        match signal.trim().to_uppercase().as_str() {
+            "SIGINT" | "INT" | "2" => Ok(libc::SIGINT),
+            "SIGHUP" | "HUP" | "1" => Ok(libc::SIGHUP),
+            "SIGTERM" | "TERM" | "15" => Ok(libc::SIGTERM),
+            "SIGKILL" | "KILL" | "9" => Ok(libc::SIGKILL),
+            "SIGTSTP" | "TSTP" => Ok(libc::SIGTSTP),
+            "SIGCONT" | "CONT" => Ok(libc::SIGCONT),
+            other => other
+                .parse::<i32>()
+                .map_err(|_| format!("Unknown signal: {}", other)),
+        }
+    }
+
+    pub fn close(&mut self, id: u32) -> Result<(), String> {
+        if !self.instances.lock().contains_key(&id) {
+            return Err("PTY not found".to_string());
+        }
+
+        // Graceful shutdown: SIGTERM, give Claude/the shell time to clean up,
+        // then SIGKILL if it's still around.
+        let _ = self.signal(id, "SIGTERM");
+        std::thread::sleep(std::time::Duration::from_millis(500));
+
+        #[cfg(unix)]
+        {
+            let still_alive = {
+                let instances = self.instances.lock();
+                instances.get(&id).and_then(|i| i.child.process_id()).is_some_and(|pid| {
+                    unsafe { libc::kill(-(pid as i32), 0) == 0 }
+                })
+            };
+            if still_alive {
+                let _ = self.signal(id, "SIGKILL");
+                std::thread::sleep(std::time::Duration::from_millis(100));
             }
+        }
 
-            // Wait for child to prevent zombies
+        let instance = self.instances.lock().remove(&id);
+        if let Some(mut instance) = instance {
+            self.pid_to_id.lock().retain(|_, v| *v != id);
+
+            // Wait for child to prevent zombies (a concurrent SIGCHLD reap may
+            // already have done this on Unix, in which case `wait()` returns
+            // an error, which is ignored).
             let _ = instance.child.wait();
+
+            #[cfg(unix)]
+            {
+                // Same requirement as `reap_children`: deregister the master fd
+                // from the poller before dropping the instance that owns it.
+                let fd = instance.master.as_raw_fd();
+                let _ = self.poller.delete(fd);
+            }
+
             drop(instance);
             Ok(())
         } else {
@@ -233,7 +720,7 @@ impl PtyManager {
 
     /// Close all PTY instances - used during shutdown
     pub fn close_all(&mut self) {
-        let ids: Vec<u32> = self.instances.keys().copied().collect();
+        let ids: Vec<u32> = self.instances.lock().keys().copied().collect();
         for id in ids {
             let _ = self.close(id);
         }
@@ -255,32 +742,56 @@ pub fn pty_spawn(
     cwd: String,
     cols: u16,
     rows: u16,
+    // Cell-pixel dimensions the frontend knows from its font metrics (e.g.
+    // xterm.js), so TIOCGWINSZ reports pixel size to graphics-capable
+    // programs (sixel, kitty/iTerm2 inline images) instead of 0x0.
+    pixel_width: Option<u16>,
+    pixel_height: Option<u16>,
+    raw: Option<bool>,
+    mode: Option<TermiosMode>,
     manager: State<Arc<Mutex<PtyManager>>>,
     app: AppHandle,
 ) -> Result<u32, String> {
     let mut manager = manager.lock();
-    manager.spawn(&cmd, &cwd, cols, rows, app)
+    manager.spawn(
+        &cmd,
+        &cwd,
+        cols,
+        rows,
+        pixel_width.unwrap_or(0),
+        pixel_height.unwrap_or(0),
+        raw.unwrap_or(false),
+        mode,
+        app,
+    )
 }
 
 #[tauri::command]
-pub fn pty_write(
+pub fn pty_set_mode(
     id: u32,
-    data: String,
+    mode: TermiosMode,
+    manager: State<Arc<Mutex<PtyManager>>>,
+) -> Result<(), String> {
+    let manager = manager.lock();
+    manager.set_mode(id, &mode)
+}
+
+#[tauri::command]
+pub fn pty_write(
+    input: PtyInput,
     manager: State<Arc<Mutex<PtyManager>>>,
 ) -> Result<(), String> {
     let mut manager = manager.lock();
-    manager.write(id, &data)
+    manager.write(input.id, &input.data)
 }
 
 #[tauri::command]
 pub fn pty_resize(
-    id: u32,
-    cols: u16,
-    rows: u16,
+    req: PtyResize,
     manager: State<Arc<Mutex<PtyManager>>>,
 ) -> Result<(), String> {
     let mut manager = manager.lock();
-    manager.resize(id, cols, rows)
+    manager.resize(req.id, req.cols, req.rows, req.pixel_width, req.pixel_height)
 }
 
 #[tauri::command]
@@ -291,3 +802,22 @@ pub fn pty_close(
     let mut manager = manager.lock();
     manager.close(id)
 }
+
+#[tauri::command]
+pub fn pty_signal(
+    id: u32,
+    signal: String,
+    manager: State<Arc<Mutex<PtyManager>>>,
+) -> Result<(), String> {
+    let manager = manager.lock();
+    manager.signal(id, &signal)
+}
+
+#[tauri::command]
+pub fn pty_foreground_process(
+    id: u32,
+    manager: State<Arc<Mutex<PtyManager>>>,
+) -> Result<Option<ForegroundProcess>, String> {
+    let manager = manager.lock();
+    manager.foreground_process(id)
+}